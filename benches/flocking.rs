@@ -0,0 +1,61 @@
+// Headless stress test for flocking. Keeps a swarm of several hundred spiders chasing a player
+// ship that moves in a circle and measures how long a frame of steering takes.
+//
+// Run with `cargo bench --bench flocking`
+use arachnophobic::{prelude::*, WINDOW_SIZE};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const FLOCKERS: usize = 600;
+const WARMUP_FRAMES: usize = 60;
+
+fn circle_player(time: Res<Time>, mut players: Query<&mut Transform, With<Player>>) {
+    let angle = time.seconds_since_startup() as f32;
+    for mut transform in players.iter_mut() {
+        transform.translation = Vec3::new(angle.cos(), angle.sin(), 0.0) * 200.0;
+    }
+}
+
+fn flocking(c: &mut Criterion) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_system(circle_player)
+        .add_system(flock);
+
+    let player = app
+        .world
+        .spawn()
+        .insert(Player {
+            index: 0,
+            movement_speed: 0.0,
+            focus_speed: 0.0,
+            focused: false,
+        })
+        .insert(Transform::default())
+        .id();
+    // A grid of spiders over the screen so they start out spread across many cells
+    let columns = (FLOCKERS as f32).sqrt().ceil() as usize;
+    for i in 0..FLOCKERS {
+        let position = Vec2::new(
+            (i % columns) as f32 / columns as f32 - 0.5,
+            (i / columns) as f32 / columns as f32 - 0.5,
+        ) * WINDOW_SIZE;
+        app.world
+            .spawn()
+            .insert(Flocking::new(FlockWeights::default()))
+            .insert(Transform::from_translation(Vec3::from((position, 0.0))))
+            .insert(EnemyTarget {
+                player: Some(player),
+            });
+    }
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+
+    c.bench_function("frame with 600 flocking spiders", |b| {
+        b.iter(|| app.update())
+    });
+}
+
+criterion_group!(benches, flocking);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+// Headless stress test for the projectile pool. Keeps several thousand projectiles on screen,
+// releasing the oldest and spawning new ones every frame, and measures how long a frame takes.
+//
+// Run with `cargo bench --bench projectile_pool`
+use std::collections::VecDeque;
+
+use arachnophobic::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const CAPACITY: usize = 8192;
+/**
+ * Projectiles kept alive once the pool has warmed up
+ */
+const ON_SCREEN: usize = 6000;
+const SPAWNED_PER_FRAME: usize = 500;
+const WARMUP_FRAMES: usize = 60;
+
+fn churn_projectiles(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut live: Local<VecDeque<Entity>>,
+    mut frame: Local<u32>,
+) {
+    *frame += 1;
+    while live.len() + SPAWNED_PER_FRAME > ON_SCREEN {
+        match live.pop_front() {
+            Some(entity) => pool.release(&mut commands, entity),
+            None => break,
+        }
+    }
+    for i in 0..SPAWNED_PER_FRAME {
+        let angle = (i as f32 + *frame as f32) * 0.37;
+        let bundle = ProjectileBundle {
+            projectile: Projectile {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * 300.0,
+            },
+            ..Default::default()
+        };
+        if let Some(entity) = pool.spawn(&mut commands, bundle) {
+            live.push_back(entity);
+        }
+    }
+}
+
+fn projectile_pool(c: &mut Criterion) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(ProjectilePool::with_capacity(CAPACITY))
+        .add_system(churn_projectiles)
+        .add_system(update_projectile);
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    let active = app
+        .world
+        .get_resource::<ProjectilePool>()
+        .expect("the pool was inserted above")
+        .active();
+    assert_eq!(active, ON_SCREEN, "the pool should keep up with the churn");
+
+    c.bench_function("frame with 6000 pooled projectiles", |b| {
+        b.iter(|| app.update())
+    });
+}
+
+criterion_group!(benches, projectile_pool);
+criterion_main!(benches);
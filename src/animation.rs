@@ -0,0 +1,338 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(AppState::Game(Running))
+                .with_system(animate_sprites)
+                .with_system(despawn_finished_animations)
+                .into(),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationMode {
+    /**
+     * Starts over after the last frame
+     */
+    Loop,
+    /**
+     * Plays once, then goes back to the default clip or holds the last frame if there is none
+     */
+    Once,
+    /**
+     * Plays once and stays on the last frame until another clip is played, it never finishes
+     */
+    Hold,
+}
+
+/**
+ * A named run of frames on a sprite sheet
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationClip {
+    /**
+     * Indices into the texture atlas in the order they are shown
+     */
+    pub frames: Vec<usize>,
+    /**
+     * Seconds each frame is shown
+     */
+    pub frame_time: f32,
+    pub mode: AnimationMode,
+}
+
+/**
+ * A texture atlas cut into a grid with the clips that can be played from it
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteSheetDefinition {
+    /**
+     * Path of the texture inside the assets folder
+     */
+    pub texture: String,
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    /**
+     * Clip played when nothing else is, also returned to after one-shot clips
+     */
+    pub default_clip: String,
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+impl SpriteSheetDefinition {
+    pub fn atlas(&self, asset_server: &AssetServer) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            asset_server.load(self.texture.as_str()),
+            self.tile_size,
+            self.columns,
+            self.rows,
+        )
+    }
+
+    pub fn animation(&self) -> SpriteAnimation {
+        SpriteAnimation::new(self.clips.clone(), &self.default_clip)
+    }
+}
+
+/**
+ * Plays named clips on a TextureAtlasSprite, see play
+ */
+#[derive(Component, Debug, Clone)]
+pub struct SpriteAnimation {
+    pub clips: HashMap<String, AnimationClip>,
+    pub default_clip: Option<String>,
+    current: String,
+    /**
+     * Position in the frames of the current clip
+     */
+    frame: usize,
+    timer: Timer,
+    finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: HashMap<String, AnimationClip>, default_clip: &str) -> Self {
+        let mut animation = Self {
+            clips,
+            default_clip: Some(default_clip.to_string()),
+            current: String::new(),
+            frame: 0,
+            timer: Timer::default(),
+            finished: false,
+        };
+        animation.play(default_clip);
+        animation
+    }
+
+    /**
+     * Plays a single one-shot clip and holds its last frame, for effects that go away afterwards
+     */
+    pub fn once(clip: AnimationClip) -> Self {
+        let mut animation = Self::new(HashMap::from([("once".to_string(), clip)]), "once");
+        animation.default_clip = None;
+        animation
+    }
+
+    /**
+     * Switches to a clip from its first frame, does nothing if it is already playing or doesn't exist
+     */
+    pub fn play(&mut self, name: &str) {
+        if self.current == name && !self.finished {
+            return;
+        }
+        if let Some(clip) = self.clips.get(name) {
+            self.timer = Timer::from_seconds(clip.frame_time, true);
+            self.current = name.to_string();
+            self.frame = 0;
+            self.finished = false;
+        }
+    }
+
+    /**
+     * Like play, but picks the clip up at the atlas index that is showing now if the clip has it,
+     * so a ship that is half banked levels out from where it is
+     */
+    pub fn play_from(&mut self, name: &str, index: usize) {
+        if self.current == name && !self.finished {
+            return;
+        }
+        self.play(name);
+        if let Some(frame) = self
+            .clips
+            .get(name)
+            .and_then(|clip| clip.frames.iter().position(|frame| *frame == index))
+        {
+            self.frame = frame;
+        }
+    }
+
+    pub fn has_clip(&self, name: &str) -> bool {
+        self.clips.contains_key(name)
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /**
+     * Whether a one-shot clip has shown its last frame, looping clips never finish
+     */
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /**
+     * The atlas index to show right now
+     */
+    pub fn index(&self) -> Option<usize> {
+        self.clips
+            .get(&self.current)
+            .and_then(|clip| clip.frames.get(self.frame))
+            .copied()
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        if self.finished || !self.timer.tick(delta).just_finished() {
+            return;
+        }
+        let (length, mode) = match self.clips.get(&self.current) {
+            Some(clip) => (clip.frames.len(), clip.mode),
+            None => return,
+        };
+        // A long frame hitch can skip several frames at once
+        self.frame += self.timer.times_finished() as usize;
+        if self.frame < length {
+            return;
+        }
+        match mode {
+            AnimationMode::Loop => self.frame %= length.max(1),
+            AnimationMode::Hold => self.frame = length.saturating_sub(1),
+            AnimationMode::Once => {
+                self.frame = length.saturating_sub(1);
+                self.finished = true;
+                if let Some(default_clip) = self.default_clip.clone() {
+                    self.play(&default_clip);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Despawns an effect once its one-shot animation is over
+ */
+#[derive(Component)]
+pub struct DespawnWhenAnimationFinished;
+
+pub fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        animation.tick(time.delta());
+        if let Some(index) = animation.index() {
+            if sprite.index != index {
+                sprite.index = index;
+            }
+        }
+    }
+}
+
+pub fn despawn_finished_animations(
+    mut commands: Commands,
+    query: Query<(Entity, &SpriteAnimation), With<DespawnWhenAnimationFinished>>,
+) {
+    for (entity, animation) in query.iter() {
+        if animation.is_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(250);
+
+    fn clip(frames: &[usize], mode: AnimationMode) -> AnimationClip {
+        AnimationClip {
+            frames: frames.to_vec(),
+            frame_time: 0.25,
+            mode,
+        }
+    }
+
+    fn animation() -> SpriteAnimation {
+        SpriteAnimation::new(
+            HashMap::from([
+                ("walk".to_string(), clip(&[0, 1, 2], AnimationMode::Loop)),
+                ("hurt".to_string(), clip(&[5, 6, 7], AnimationMode::Once)),
+                ("bank".to_string(), clip(&[3, 4], AnimationMode::Hold)),
+            ]),
+            "walk",
+        )
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut animation = animation();
+        assert_eq!(animation.index(), Some(0));
+        animation.tick(FRAME);
+        animation.tick(FRAME);
+        assert_eq!(animation.index(), Some(2));
+        animation.tick(FRAME);
+        assert_eq!(animation.index(), Some(0));
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn once_returns_to_default_clip() {
+        let mut animation = animation();
+        animation.play("hurt");
+        assert_eq!(animation.index(), Some(5));
+        animation.tick(FRAME);
+        animation.tick(FRAME);
+        assert_eq!(animation.index(), Some(7));
+        animation.tick(FRAME);
+        assert_eq!(animation.current(), "walk");
+        assert_eq!(animation.index(), Some(0));
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn once_without_default_holds_last_frame() {
+        let mut animation = SpriteAnimation::once(clip(&[5, 6, 7], AnimationMode::Once));
+        for _ in 0..5 {
+            animation.tick(FRAME);
+        }
+        assert_eq!(animation.index(), Some(7));
+        assert!(animation.is_finished());
+    }
+
+    #[test]
+    fn hold_stays_on_last_frame() {
+        let mut animation = animation();
+        animation.play("bank");
+        for _ in 0..4 {
+            animation.tick(FRAME);
+        }
+        assert_eq!(animation.current(), "bank");
+        assert_eq!(animation.index(), Some(4));
+        assert!(!animation.is_finished());
+
+        // Playing it again while it holds doesn't start it over
+        animation.play("bank");
+        assert_eq!(animation.index(), Some(4));
+    }
+
+    #[test]
+    fn long_hitch_skips_frames() {
+        let mut animation = animation();
+        animation.tick(FRAME * 2);
+        assert_eq!(animation.index(), Some(2));
+
+        animation.play("hurt");
+        animation.tick(FRAME * 7);
+        assert_eq!(animation.current(), "walk");
+    }
+
+    #[test]
+    fn play_from_picks_up_at_the_shown_frame() {
+        let mut animation = animation();
+        animation.play_from("hurt", 6);
+        assert_eq!(animation.index(), Some(6));
+        animation.play_from("bank", 0);
+        assert_eq!(animation.index(), Some(3));
+    }
+}
@@ -7,18 +7,33 @@ pub struct TextureAtlasAssets {
     #[asset(texture_atlas(tile_size_x = 64., tile_size_y = 64., columns = 11, rows = 1))]
     #[asset(path = "player_ship/roll/roll_sheet.png")]
     pub player_ship: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 16., tile_size_y = 16., columns = 4, rows = 1))]
+    #[asset(path = "pickup/pickup_sheet.png")]
+    pub pickup: Handle<TextureAtlas>,
 }
 
 #[derive(AssetCollection)]
 pub struct SpriteAssets {
     #[asset(path = "projectile/projectile_01.png")]
     pub projectile: Handle<Image>,
+    #[asset(path = "projectile/beam_projectile.png")]
+    pub beam_projectile: Handle<Image>,
     #[asset(path = "enemy/spider_medium.png")]
     pub spider_medium: Handle<Image>,
     #[asset(path = "enemy/spider_small.png")]
     pub spider_small: Handle<Image>,
     #[asset(path = "enemy/spider_tiny.png")]
     pub spider_tiny: Handle<Image>,
+    /**
+     * Walk, attack, hurt and die clips of the spiders, enemy definitions cut their animations
+     * out of these so they are loaded before a stage starts
+     */
+    #[asset(path = "enemy/spider_medium_sheet.png")]
+    pub spider_medium_sheet: Handle<Image>,
+    #[asset(path = "enemy/spider_small_sheet.png")]
+    pub spider_small_sheet: Handle<Image>,
+    #[asset(path = "enemy/spider_tiny_sheet.png")]
+    pub spider_tiny_sheet: Handle<Image>,
 }
 
 #[derive(AssetCollection)]
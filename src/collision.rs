@@ -4,4 +4,7 @@ use crate::prelude::*;
 pub enum CollisionLayer {
     Player,
     Enemy,
+    EnemyProjectile,
+    Pickup,
+    Hazard,
 }
@@ -1,7 +1,29 @@
 use crate::prelude::*;
 
-#[derive(Component)]
+#[derive(Component, PartialEq, Eq)]
 pub enum Faction {
     Player,
     Spiders,
 }
+
+/**
+ * The player that is credited for damage dealt by this entity
+ */
+#[derive(Component, Clone, Copy)]
+pub struct Owner {
+    pub player: usize,
+}
+
+/**
+ * Score awarded for killing this entity
+ */
+#[derive(Component)]
+pub struct Points {
+    pub value: u32,
+}
+
+/**
+ * The camera the game world is rendered with
+ */
+#[derive(Component)]
+pub struct MainCamera;
@@ -0,0 +1,617 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use bevy::input::InputSystem;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const BINDINGS_PATH: &str = "bindings.ron";
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerBindings::load_or_default(BINDINGS_PATH));
+        app.init_resource::<ActionState>();
+        app.init_resource::<PlayerActions>();
+        app.init_resource::<PlayerGamepads>();
+        app.add_system_to_stage(CoreStage::PreUpdate, gamepad_connections.after(InputSystem));
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            update_action_state
+                .after(InputSystem)
+                .after(gamepad_connections),
+        );
+    }
+}
+
+/**
+ * Everything the player can do, independent of the keys that are bound to it
+ */
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Beam,
+    Bomb,
+    Focus,
+    Dodge,
+    Pause,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    /**
+     * Actions that can be changed from the settings screen, in the order they are listed
+     */
+    pub const REBINDABLE: [Action; 10] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Beam,
+        Action::Bomb,
+        Action::Focus,
+        Action::Dodge,
+        Action::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Fire => "Fire",
+            Action::Beam => "Beam",
+            Action::Bomb => "Bomb",
+            Action::Focus => "Focus",
+            Action::Dodge => "Dodge",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+        }
+    }
+
+    /**
+     * Menu actions can share inputs with gameplay actions since they are never used at the same time
+     */
+    pub fn is_menu(&self) -> bool {
+        matches!(self, Action::Confirm | Action::Back)
+    }
+}
+
+/**
+ * Maps actions to keys and gamepad buttons, the first key or button of every action
+ * is the one changed in the settings
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub keys: HashMap<Action, Vec<KeyCode>>,
+    #[serde(default = "default_buttons")]
+    pub buttons: HashMap<Action, Vec<GamepadButtonType>>,
+    /**
+     * Stick deflection below which analog movement is ignored, from 0 to 1
+     */
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f32,
+}
+
+fn default_buttons() -> HashMap<Action, Vec<GamepadButtonType>> {
+    use GamepadButtonType::*;
+    HashMap::from([
+        (Action::MoveUp, vec![DPadUp]),
+        (Action::MoveDown, vec![DPadDown]),
+        (Action::MoveLeft, vec![DPadLeft]),
+        (Action::MoveRight, vec![DPadRight]),
+        (Action::Fire, vec![South, RightTrigger2]),
+        (Action::Beam, vec![West, RightTrigger]),
+        (Action::Bomb, vec![North]),
+        (Action::Focus, vec![LeftTrigger2, LeftTrigger]),
+        (Action::Dodge, vec![East]),
+        (Action::Pause, vec![Start]),
+        (Action::Confirm, vec![South]),
+        (Action::Back, vec![East]),
+    ])
+}
+
+fn default_deadzone() -> f32 {
+    0.2
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::from([
+                (Action::MoveUp, vec![KeyCode::W, KeyCode::Up]),
+                (Action::MoveDown, vec![KeyCode::S, KeyCode::Down]),
+                (Action::MoveLeft, vec![KeyCode::A, KeyCode::Left]),
+                (Action::MoveRight, vec![KeyCode::D, KeyCode::Right]),
+                (Action::Fire, vec![KeyCode::Space]),
+                (Action::Beam, vec![KeyCode::C]),
+                (Action::Bomb, vec![KeyCode::X]),
+                (Action::Focus, vec![KeyCode::LShift]),
+                (Action::Dodge, vec![KeyCode::Z]),
+                (Action::Pause, vec![KeyCode::P]),
+                (Action::Confirm, vec![KeyCode::Return]),
+                (Action::Back, vec![KeyCode::Back]),
+            ]),
+            buttons: default_buttons(),
+            deadzone: default_deadzone(),
+        }
+    }
+}
+
+impl InputBindings {
+    /**
+     * Keyboard controls on the right hand side of the keyboard so two players can share it
+     */
+    pub fn second_player() -> Self {
+        Self {
+            keys: HashMap::from([
+                (Action::MoveUp, vec![KeyCode::I]),
+                (Action::MoveDown, vec![KeyCode::K]),
+                (Action::MoveLeft, vec![KeyCode::J]),
+                (Action::MoveRight, vec![KeyCode::L]),
+                (Action::Fire, vec![KeyCode::U]),
+                (Action::Beam, vec![KeyCode::O]),
+                (Action::Bomb, vec![KeyCode::Y]),
+                (Action::Focus, vec![KeyCode::H]),
+                (Action::Dodge, vec![KeyCode::N]),
+                (Action::Pause, vec![KeyCode::M]),
+                // Like on a gamepad, fire confirms and dodge goes back in the menus
+                (Action::Confirm, vec![KeyCode::U]),
+                (Action::Back, vec![KeyCode::N]),
+            ]),
+            buttons: default_buttons(),
+            deadzone: default_deadzone(),
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.buttons.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /**
+     * Replaces the primary key of an action, fails with the conflicting action if the key is taken
+     */
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        rebind(&mut self.keys, action, key)
+    }
+
+    /**
+     * Replaces the primary gamepad button of an action, fails with the conflicting action if the button is taken
+     */
+    pub fn rebind_button(
+        &mut self,
+        action: Action,
+        button: GamepadButtonType,
+    ) -> Result<(), Action> {
+        rebind(&mut self.buttons, action, button)
+    }
+}
+
+fn rebind<T: PartialEq>(
+    bindings: &mut HashMap<Action, Vec<T>>,
+    action: Action,
+    input: T,
+) -> Result<(), Action> {
+    // Only actions that can be used at the same time conflict with each other
+    let conflict = bindings.iter().find(|(other, inputs)| {
+        **other != action && other.is_menu() == action.is_menu() && inputs.contains(&input)
+    });
+    if let Some((other, _)) = conflict {
+        return Err(*other);
+    }
+    let inputs = bindings.entry(action).or_default();
+    inputs.retain(|i| *i != input);
+    if inputs.is_empty() {
+        inputs.push(input);
+    } else {
+        inputs[0] = input;
+    }
+    Ok(())
+}
+
+/**
+ * An input that is already bound to an action of a player
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindingConflict {
+    pub player: usize,
+    pub action: Action,
+}
+
+/**
+ * The bindings of every player, saved to and loaded from the bindings config file
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerBindings {
+    pub players: Vec<InputBindings>,
+}
+
+impl Default for PlayerBindings {
+    fn default() -> Self {
+        Self {
+            players: vec![InputBindings::default(), InputBindings::second_player()],
+        }
+    }
+}
+
+impl PlayerBindings {
+    /**
+     * Reads bindings from a config file, falling back to the defaults if it is missing or invalid
+     */
+    pub fn load_or_default(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => match ron::from_str::<Self>(&contents) {
+                Ok(mut bindings) => {
+                    // Fill in players and actions missing from older config files
+                    let defaults = Self::default();
+                    for (player, default) in bindings.players.iter_mut().zip(&defaults.players) {
+                        for (action, keys) in &default.keys {
+                            player.keys.entry(*action).or_insert_with(|| keys.clone());
+                        }
+                        for (action, buttons) in &default.buttons {
+                            player
+                                .buttons
+                                .entry(*action)
+                                .or_insert_with(|| buttons.clone());
+                        }
+                    }
+                    bindings
+                        .players
+                        .extend(defaults.players.into_iter().skip(bindings.players.len()));
+                    bindings
+                }
+                Err(err) => {
+                    warn!("Could not parse {}, using default bindings: {}", path, err);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    /**
+     * Replaces the primary key of a player's action. Everyone shares the keyboard, so the key
+     * can't be taken by an action of any player that is used at the same time.
+     * Gamepad buttons only need checking per player, see InputBindings::rebind_button
+     */
+    pub fn rebind_key(
+        &mut self,
+        player: usize,
+        action: Action,
+        key: KeyCode,
+    ) -> Result<(), BindingConflict> {
+        let taken = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != player)
+            .find_map(|(index, bindings)| {
+                bindings
+                    .keys
+                    .iter()
+                    .find(|(other, keys)| {
+                        other.is_menu() == action.is_menu() && keys.contains(&key)
+                    })
+                    .map(|(other, _)| BindingConflict {
+                        player: index,
+                        action: *other,
+                    })
+            });
+        if let Some(conflict) = taken {
+            return Err(conflict);
+        }
+        self.players[player]
+            .rebind(action, key)
+            .map_err(|other| BindingConflict {
+                player,
+                action: other,
+            })
+    }
+
+    pub fn save(&self, path: &str) {
+        let result = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| fs::write(path, contents).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("Could not save bindings to {}: {}", path, err);
+        }
+    }
+}
+
+/**
+ * Which actions are held down this frame, gameplay reads this instead of the raw input
+ */
+#[derive(Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    movement: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /**
+     * Direction to move in, analog sticks give a length below 1 when not fully deflected
+     */
+    pub fn movement(&self) -> Vec2 {
+        self.movement
+    }
+}
+
+/**
+ * The actions of each player, indexed by `Player::index`.
+ * The ActionState resource combines all of them and is used by the menus
+ */
+pub struct PlayerActions {
+    pub players: Vec<ActionState>,
+}
+
+impl Default for PlayerActions {
+    fn default() -> Self {
+        Self {
+            players: (0..MAX_PLAYERS).map(|_| ActionState::default()).collect(),
+        }
+    }
+}
+
+impl PlayerActions {
+    pub fn get(&self, player: usize) -> &ActionState {
+        &self.players[player]
+    }
+}
+
+/**
+ * The gamepad each player is using, pads are handed out in the order they are connected
+ */
+pub struct PlayerGamepads {
+    pub players: Vec<Option<Gamepad>>,
+    /**
+     * Connected gamepads that are waiting for a free player
+     */
+    waiting: Vec<Gamepad>,
+}
+
+impl Default for PlayerGamepads {
+    fn default() -> Self {
+        Self {
+            players: vec![None; MAX_PLAYERS],
+            waiting: Vec::new(),
+        }
+    }
+}
+
+pub fn gamepad_connections(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut gamepads: ResMut<PlayerGamepads>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                info!("Gamepad {:?} connected", gamepad);
+                gamepads.waiting.push(*gamepad);
+            }
+            GamepadEventType::Disconnected => {
+                info!("Gamepad {:?} disconnected", gamepad);
+                gamepads.waiting.retain(|g| g != gamepad);
+                for slot in gamepads.players.iter_mut() {
+                    if *slot == Some(*gamepad) {
+                        *slot = None;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Hand waiting gamepads to players that have none
+    let PlayerGamepads { players, waiting } = &mut *gamepads;
+    for slot in players.iter_mut().filter(|slot| slot.is_none()) {
+        if waiting.is_empty() {
+            break;
+        }
+        *slot = Some(waiting.remove(0));
+    }
+}
+
+pub fn update_action_state(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<PlayerGamepads>,
+    bindings: Res<PlayerBindings>,
+    mut player_actions: ResMut<PlayerActions>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.pressed.clear();
+    actions.just_pressed.clear();
+    actions.movement = Vec2::ZERO;
+
+    for (player, state) in player_actions.players.iter_mut().enumerate() {
+        let player_bindings = match bindings.players.get(player) {
+            Some(player_bindings) => player_bindings,
+            None => continue,
+        };
+        let gamepad = gamepads.players.get(player).copied().flatten();
+        *state = read_actions(
+            player_bindings,
+            gamepad,
+            &keyboard_input,
+            &gamepad_buttons,
+            &gamepad_axes,
+        );
+
+        actions.pressed.extend(state.pressed.iter().copied());
+        actions
+            .just_pressed
+            .extend(state.just_pressed.iter().copied());
+        if actions.movement == Vec2::ZERO {
+            actions.movement = state.movement;
+        }
+    }
+}
+
+fn read_actions(
+    bindings: &InputBindings,
+    gamepad: Option<Gamepad>,
+    keyboard_input: &Input<KeyCode>,
+    gamepad_buttons: &Input<GamepadButton>,
+    gamepad_axes: &Axis<GamepadAxis>,
+) -> ActionState {
+    let mut actions = ActionState::default();
+    for action in bindings.keys.keys().chain(bindings.buttons.keys()) {
+        let keys = bindings.keys(*action);
+        let buttons: Vec<GamepadButton> = match gamepad {
+            Some(gamepad) => bindings
+                .buttons(*action)
+                .iter()
+                .map(|button| GamepadButton(gamepad, *button))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if keys.iter().any(|key| keyboard_input.pressed(*key))
+            || buttons
+                .iter()
+                .any(|button| gamepad_buttons.pressed(*button))
+        {
+            actions.pressed.insert(*action);
+        }
+        if keys.iter().any(|key| keyboard_input.just_pressed(*key))
+            || buttons
+                .iter()
+                .any(|button| gamepad_buttons.just_pressed(*button))
+        {
+            actions.just_pressed.insert(*action);
+        }
+    }
+
+    let mut movement = Vec2::ZERO;
+    if actions.pressed(Action::MoveUp) {
+        movement.y += 1.0;
+    }
+    if actions.pressed(Action::MoveDown) {
+        movement.y -= 1.0;
+    }
+    if actions.pressed(Action::MoveLeft) {
+        movement.x -= 1.0;
+    }
+    if actions.pressed(Action::MoveRight) {
+        movement.x += 1.0;
+    }
+
+    // Digital input wins over the stick so keyboard and D-pad always move at full speed
+    if let Some(gamepad) = gamepad.filter(|_| movement == Vec2::ZERO) {
+        let stick = Vec2::new(
+            gamepad_axes
+                .get(GamepadAxis(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or(0.0),
+            gamepad_axes
+                .get(GamepadAxis(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or(0.0),
+        );
+        // Rescale so movement starts from zero right at the edge of the deadzone
+        let deflection = stick.length();
+        if deflection > bindings.deadzone {
+            let magnitude = ((deflection - bindings.deadzone) / (1.0 - bindings.deadzone)).min(1.0);
+            movement = stick / deflection * magnitude;
+        }
+    }
+
+    actions.movement = movement;
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_conflict_across_players() {
+        let mut bindings = PlayerBindings::default();
+
+        assert_eq!(
+            bindings.rebind_key(1, Action::Fire, KeyCode::Space),
+            Err(BindingConflict {
+                player: 0,
+                action: Action::Fire,
+            })
+        );
+        assert_eq!(bindings.players[1].keys(Action::Fire), &[KeyCode::U]);
+
+        assert_eq!(
+            bindings.rebind_key(0, Action::Bomb, KeyCode::U),
+            Err(BindingConflict {
+                player: 1,
+                action: Action::Fire,
+            })
+        );
+        assert_eq!(bindings.players[0].keys(Action::Bomb), &[KeyCode::X]);
+    }
+
+    #[test]
+    fn keys_conflict_within_a_player() {
+        let mut bindings = PlayerBindings::default();
+
+        assert_eq!(
+            bindings.rebind_key(0, Action::Bomb, KeyCode::Space),
+            Err(BindingConflict {
+                player: 0,
+                action: Action::Fire,
+            })
+        );
+        assert_eq!(bindings.rebind_key(1, Action::Bomb, KeyCode::T), Ok(()));
+        assert_eq!(bindings.players[1].keys(Action::Bomb), &[KeyCode::T]);
+    }
+
+    #[test]
+    fn second_player_can_pause_and_use_menus() {
+        let bindings = PlayerBindings::default();
+        for action in [Action::Pause, Action::Confirm, Action::Back] {
+            let keys = bindings.players[1].keys(action);
+            assert!(!keys.is_empty(), "P2 has no key for {}", action.name());
+            // None of them may be taken by the first player
+            for (other, other_keys) in &bindings.players[0].keys {
+                if other.is_menu() == action.is_menu() {
+                    assert!(keys.iter().all(|key| !other_keys.contains(key)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn buttons_are_per_player() {
+        let mut bindings = PlayerBindings::default();
+
+        assert_eq!(
+            bindings.players[0].rebind_button(Action::Dodge, GamepadButtonType::Select),
+            Ok(())
+        );
+        assert_eq!(
+            bindings.players[1].rebind_button(Action::Dodge, GamepadButtonType::Select),
+            Ok(())
+        );
+    }
+}
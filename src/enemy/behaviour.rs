@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * What moves an enemy from one behaviour state to another
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Trigger {
+    /**
+     * Seconds spent in the current state
+     */
+    After(f32),
+    /**
+     * The targeted player is closer than this many pixels
+     */
+    PlayerWithin(f32),
+    /**
+     * The targeted player is further away than this many pixels
+     */
+    PlayerBeyond(f32),
+    /**
+     * Health dropped below this fraction of what the enemy spawned with
+     */
+    HealthBelow(f32),
+    /**
+     * The stage sent a signal with this name, see WaveSignalEvent
+     */
+    WaveSignal(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transition {
+    pub to: String,
+    pub when: Trigger,
+}
+
+/**
+ * What a state does with the enemy's BulletEmitter
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponChoice {
+    /**
+     * Leave the emitter as it is, for bosses whose phases follow their parts
+     */
+    Keep,
+    HoldFire,
+    /**
+     * Fire the emitter phase with this index
+     */
+    Phase(usize),
+}
+
+impl Default for WeaponChoice {
+    fn default() -> Self {
+        WeaponChoice::Keep
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviourState {
+    /**
+     * How the enemy moves in this state, it keeps its current movement if left out
+     */
+    #[serde(default)]
+    pub movement: Option<Movement>,
+    #[serde(default)]
+    pub weapon: WeaponChoice,
+    /**
+     * Checked in order, the first one that triggers wins
+     */
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/**
+ * The states an enemy archetype goes through, set in its definition
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviourDefinition {
+    pub initial: String,
+    pub states: HashMap<String, BehaviourState>,
+}
+
+/**
+ * Runs an enemy through the states of its behaviour, each state sets how it moves and fires
+ */
+#[derive(Component)]
+pub struct StateMachine {
+    pub behaviour: BehaviourDefinition,
+    current: String,
+    /**
+     * Seconds spent in the current state
+     */
+    elapsed: f32,
+    entered: bool,
+    /**
+     * Health when the machine first ran, HealthBelow is relative to it
+     */
+    max_health: Option<i32>,
+}
+
+impl StateMachine {
+    pub fn new(behaviour: BehaviourDefinition) -> Self {
+        Self {
+            current: behaviour.initial.clone(),
+            behaviour,
+            elapsed: 0.0,
+            entered: false,
+            max_health: None,
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    fn switch(&mut self, state: &str) {
+        if !self.behaviour.states.contains_key(state) {
+            warn!("There is no behaviour state called {}", state);
+            return;
+        }
+        self.current = state.to_string();
+        self.elapsed = 0.0;
+        self.entered = false;
+    }
+}
+
+pub fn run_state_machines(
+    time: Res<Time>,
+    mut wave_signals: EventReader<WaveSignalEvent>,
+    mut machines: Query<(
+        &mut StateMachine,
+        &Transform,
+        &Health,
+        Option<&EnemyTarget>,
+        Option<&mut EnemyMovement>,
+        Option<&mut BulletEmitter>,
+    )>,
+    players: Query<&Transform, (With<Player>, Without<StateMachine>)>,
+) {
+    let signals: Vec<String> = wave_signals
+        .iter()
+        .map(|event| event.name.clone())
+        .collect();
+
+    for (mut machine, transform, health, target, movement, emitter) in machines.iter_mut() {
+        let max_health = *machine.max_health.get_or_insert(health.value.max(1));
+        machine.elapsed += time.delta_seconds();
+
+        let position = transform.translation.truncate();
+        let distance = target
+            .and_then(|target| target.player)
+            .and_then(|player| players.get(player).ok())
+            .map(|player| player.translation.truncate().distance(position));
+        let next = machine
+            .behaviour
+            .states
+            .get(&machine.current)
+            .and_then(|state| {
+                state
+                    .transitions
+                    .iter()
+                    .find(|transition| match &transition.when {
+                        Trigger::After(seconds) => machine.elapsed >= *seconds,
+                        Trigger::PlayerWithin(range) => distance.map_or(false, |d| d < *range),
+                        Trigger::PlayerBeyond(range) => distance.map_or(false, |d| d > *range),
+                        Trigger::HealthBelow(fraction) => {
+                            (health.value as f32) < max_health as f32 * fraction
+                        }
+                        Trigger::WaveSignal(name) => signals.contains(name),
+                    })
+            })
+            .map(|transition| transition.to.clone());
+        if let Some(next) = next {
+            machine.switch(&next);
+        }
+
+        if machine.entered {
+            continue;
+        }
+        machine.entered = true;
+        let state = match machine.behaviour.states.get(&machine.current) {
+            Some(state) => state,
+            None => continue,
+        };
+        if let (Some(mut movement), Some(new_movement)) = (movement, &state.movement) {
+            movement.set(new_movement.clone(), position);
+        }
+        if let Some(mut emitter) = emitter {
+            match state.weapon {
+                WeaponChoice::Keep => (),
+                WeaponChoice::HoldFire => emitter.firing = false,
+                WeaponChoice::Phase(phase) => {
+                    emitter.firing = true;
+                    emitter.set_phase(phase);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::utils::Instant;
+
+    use super::*;
+
+    /**
+     * A single enemy running a state machine, moved along by stepping the clock
+     */
+    struct Harness {
+        world: World,
+        stage: SystemStage,
+        enemy: Entity,
+        now: Instant,
+    }
+
+    impl Harness {
+        fn new(transition: Trigger) -> Self {
+            let state = |transitions| BehaviourState {
+                movement: None,
+                weapon: WeaponChoice::Keep,
+                transitions,
+            };
+            let behaviour = BehaviourDefinition {
+                initial: "idle".to_string(),
+                states: HashMap::from([
+                    (
+                        "idle".to_string(),
+                        state(vec![Transition {
+                            to: "next".to_string(),
+                            when: transition,
+                        }]),
+                    ),
+                    ("next".to_string(), state(Vec::new())),
+                ]),
+            };
+
+            let now = Instant::now();
+            let mut time = Time::default();
+            time.update_with_instant(now);
+            let mut world = World::new();
+            world.insert_resource(time);
+            world.insert_resource(Events::<WaveSignalEvent>::default());
+            let enemy = world
+                .spawn()
+                .insert(StateMachine::new(behaviour))
+                .insert(Transform::default())
+                .insert(Health { value: 100 })
+                .id();
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(run_state_machines);
+            Self {
+                world,
+                stage,
+                enemy,
+                now,
+            }
+        }
+
+        fn run(&mut self, seconds: f32) {
+            self.now += Duration::from_secs_f32(seconds);
+            let now = self.now;
+            self.world
+                .get_resource_mut::<Time>()
+                .unwrap()
+                .update_with_instant(now);
+            self.stage.run(&mut self.world);
+        }
+
+        fn state(&self) -> &str {
+            self.world
+                .get::<StateMachine>(self.enemy)
+                .unwrap()
+                .current()
+        }
+    }
+
+    #[test]
+    fn after_waits_for_the_time_in_the_state() {
+        let mut harness = Harness::new(Trigger::After(1.0));
+        harness.run(0.5);
+        assert_eq!(harness.state(), "idle");
+        harness.run(0.6);
+        assert_eq!(harness.state(), "next");
+    }
+
+    #[test]
+    fn health_below_is_relative_to_starting_health() {
+        let mut harness = Harness::new(Trigger::HealthBelow(0.5));
+        harness.run(0.1);
+        assert_eq!(harness.state(), "idle");
+
+        harness
+            .world
+            .get_mut::<Health>(harness.enemy)
+            .unwrap()
+            .value = 60;
+        harness.run(0.1);
+        assert_eq!(harness.state(), "idle");
+
+        harness
+            .world
+            .get_mut::<Health>(harness.enemy)
+            .unwrap()
+            .value = 40;
+        harness.run(0.1);
+        assert_eq!(harness.state(), "next");
+    }
+
+    #[test]
+    fn wave_signal_needs_the_right_name() {
+        let mut harness = Harness::new(Trigger::WaveSignal("boss_incoming".to_string()));
+        let send = |harness: &mut Harness, name: &str| {
+            harness
+                .world
+                .get_resource_mut::<Events<WaveSignalEvent>>()
+                .unwrap()
+                .send(WaveSignalEvent {
+                    name: name.to_string(),
+                });
+        };
+
+        send(&mut harness, "something_else");
+        harness.run(0.1);
+        assert_eq!(harness.state(), "idle");
+
+        send(&mut harness, "boss_incoming");
+        harness.run(0.1);
+        assert_eq!(harness.state(), "next");
+    }
+}
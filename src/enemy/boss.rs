@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * The root of a boss spider, it has no collider of its own and takes damage through its parts.
+ * Its Health only drops to zero once the head is destroyed, which lets death_system remove
+ * the whole boss.
+ */
+#[derive(Component)]
+pub struct BossSpider {
+    pub legs_left: u32,
+    pub abdomen_intact: bool,
+}
+
+impl BossSpider {
+    /**
+     * Every lost body part brings the boss one attack phase further
+     */
+    pub fn phase(&self) -> usize {
+        (self.legs_left == 0) as usize + (!self.abdomen_intact) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BossPartKind {
+    Leg,
+    Abdomen,
+    /**
+     * The weak point, the boss dies with it
+     */
+    Head,
+}
+
+/**
+ * A destructible part of a boss, parts are not removed by death_system, see boss_part_death
+ */
+#[derive(Component)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub kind: BossPartKind,
+}
+
+#[derive(Bundle)]
+pub struct BossPartBundle {
+    #[bundle]
+    pub sprite: SpriteBundle,
+    pub part: BossPart,
+    pub enemy: Enemy,
+    pub faction: Faction,
+    pub health: Health,
+    pub shield: Shield,
+    pub armor: Armor,
+    pub hit_flash: HitFlash,
+    pub last_hit_by: LastHitBy,
+    pub points: Points,
+    pub rigidbody: RigidBody,
+    pub collision_shape: CollisionShape,
+    pub collision_layers: CollisionLayers,
+}
+
+impl BossPartBundle {
+    pub fn new(boss: Entity, definition: &PartDefinition, texture: Handle<Image>, z: f32) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: definition.color(),
+                    flip_x: definition.flip_x,
+                    custom_size: Some(Vec2::splat(definition.size)),
+                    ..Default::default()
+                },
+                texture,
+                transform: Transform::from_translation(Vec3::from((definition.offset, z))),
+                ..Default::default()
+            },
+            part: BossPart {
+                boss,
+                kind: definition.kind,
+            },
+            enemy: Enemy,
+            faction: Faction::Spiders,
+            health: Health {
+                value: definition.health,
+            },
+            shield: Shield::new(definition.shield),
+            armor: Armor {
+                reduction: definition.armor,
+            },
+            hit_flash: HitFlash::default(),
+            last_hit_by: LastHitBy::default(),
+            points: Points {
+                value: definition.points,
+            },
+            rigidbody: RigidBody::KinematicPositionBased,
+            collision_shape: CollisionShape::Sphere {
+                radius: definition.size / 2.0,
+            },
+            collision_layers: CollisionLayers::none()
+                .with_group(CollisionLayer::Enemy)
+                .with_masks(&[CollisionLayer::Player]),
+        }
+    }
+}
+
+/**
+ * Spawns the boss root with its parts as children, later parts are drawn on top of earlier ones
+ */
+pub fn spawn_boss(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    enemy_type: &EnemyType,
+    definition: &EnemyDefinition,
+    position: Vec2,
+) -> Entity {
+    let count = |kind| {
+        definition
+            .parts
+            .iter()
+            .filter(|part| part.kind == kind)
+            .count()
+    };
+    let mut boss = commands.spawn();
+    boss.insert(Transform::from_translation(Vec3::from((position, 0.0))))
+        .insert(GlobalTransform::default())
+        .insert(BossSpider {
+            legs_left: count(BossPartKind::Leg) as u32,
+            abdomen_intact: count(BossPartKind::Abdomen) > 0,
+        })
+        .insert(enemy_type.clone())
+        .insert(Faction::Spiders)
+        .insert(Health { value: 1 })
+        .insert(NotifyDeath)
+        .insert(LastHitBy::default())
+        .insert(Points {
+            value: definition.points,
+        })
+        .insert(EnemyTarget::default())
+        .insert(Telegraph::default())
+        .insert(EnemyMovement::new(definition.movement.clone(), position));
+    if !definition.weapon.is_empty() {
+        boss.insert(BulletEmitter::new(definition.weapon.clone()));
+    }
+    if let Some(behaviour) = &definition.behaviour {
+        boss.insert(StateMachine::new(behaviour.clone()));
+    }
+
+    let root = boss.id();
+    boss.with_children(|parent| {
+        for (i, part) in definition.parts.iter().enumerate() {
+            let texture = asset_server.load(part.sprite.as_str());
+            parent.spawn_bundle(BossPartBundle::new(
+                root,
+                part,
+                texture,
+                0.1 + 0.01 * i as f32,
+            ));
+        }
+    });
+    root
+}
+
+/**
+ * Removes destroyed parts without touching the rest of the boss, moves the boss
+ * to its next attack phase and kills it once the head falls
+ */
+pub fn boss_part_death(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    parts: Query<(Entity, &BossPart, &Health, &LastHitBy, &Points)>,
+    mut bosses: Query<
+        (
+            &mut BossSpider,
+            &mut Health,
+            &mut LastHitBy,
+            Option<&mut BulletEmitter>,
+        ),
+        Without<BossPart>,
+    >,
+) {
+    for (entity, part, health, part_hit_by, points) in parts.iter() {
+        if health.value > 0 {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        if let Some(slot) = part_hit_by
+            .player
+            .and_then(|player| players.slots.get_mut(player))
+        {
+            slot.score += points.value;
+        }
+
+        let (mut boss, mut boss_health, mut boss_hit_by, emitter) = match bosses.get_mut(part.boss)
+        {
+            Ok(boss) => boss,
+            Err(_) => continue,
+        };
+        match part.kind {
+            BossPartKind::Leg => boss.legs_left = boss.legs_left.saturating_sub(1),
+            BossPartKind::Abdomen => boss.abdomen_intact = false,
+            BossPartKind::Head => {
+                boss_health.value = 0;
+                boss_hit_by.player = part_hit_by.player;
+            }
+        }
+        if let Some(mut emitter) = emitter {
+            emitter.set_phase(boss.phase());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::prelude::*;
+
+/**
+ * Played while nothing else is, the default clip of enemy sprite sheets
+ */
+pub const WALK_CLIP: &str = "walk";
+/**
+ * Played while an attack is telegraphed
+ */
+pub const ATTACK_CLIP: &str = "attack";
+pub const HURT_CLIP: &str = "hurt";
+/**
+ * Played once by an effect left behind where the enemy died
+ */
+pub const DIE_CLIP: &str = "die";
+
+pub fn play_hurt_clips(
+    mut on_damage: EventReader<OnDamageEvent>,
+    mut animations: Query<&mut SpriteAnimation, With<Enemy>>,
+) {
+    for event in on_damage.iter() {
+        if let Ok(mut animation) = animations.get_mut(event.entity) {
+            // Don't cut the wind up of an attack short
+            if animation.current() != ATTACK_CLIP {
+                animation.play(HURT_CLIP);
+            }
+        }
+    }
+}
+
+pub fn play_attack_clips(mut query: Query<(&Telegraph, &mut SpriteAnimation), Changed<Telegraph>>) {
+    for (telegraph, mut animation) in query.iter_mut() {
+        if telegraph.is_active() {
+            animation.play(ATTACK_CLIP);
+        } else if animation.current() == ATTACK_CLIP {
+            animation.play(WALK_CLIP);
+        }
+    }
+}
+
+/**
+ * Leaves the die clip of an enemy's sprite sheet playing where it died
+ */
+pub fn spawn_death_clips(
+    mut commands: Commands,
+    mut death_event: EventReader<OnDeathEvent>,
+    definitions: Res<EnemyDefinitions>,
+) {
+    for event in death_event.iter() {
+        let enemy_type = match &event.enemy_type {
+            Some(enemy_type) => enemy_type,
+            None => continue,
+        };
+        let (definition, texture_atlas) =
+            match (definitions.get(enemy_type), definitions.atlas(enemy_type)) {
+                (Some(definition), Some(texture_atlas)) => (definition, texture_atlas),
+                _ => continue,
+            };
+        let clip = match definition
+            .animation
+            .as_ref()
+            .and_then(|sheet| sheet.clips.get(DIE_CLIP))
+        {
+            Some(clip) => clip.clone(),
+            None => continue,
+        };
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: definition.color(),
+                    custom_size: Some(Vec2::splat(definition.size)),
+                    ..Default::default()
+                },
+                texture_atlas,
+                transform: Transform::from_translation(Vec3::from((event.position, -0.1))),
+                ..Default::default()
+            })
+            .insert(SpriteAnimation::once(clip))
+            .insert(DespawnWhenAnimationFinished);
+    }
+}
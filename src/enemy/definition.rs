@@ -0,0 +1,337 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const ENEMY_DEFINITIONS_PATH: &str = "assets/data/enemies.ron";
+
+fn white() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+/**
+ * Everything that makes up an enemy archetype, read from a data file so new spiders
+ * can be added without recompiling
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyDefinition {
+    /**
+     * Path of the texture inside the assets folder, unused for enemies made of parts
+     * and enemies with an animated sprite sheet
+     */
+    #[serde(default)]
+    pub sprite: String,
+    /**
+     * Animated sprite sheet with walk, attack, hurt and die clips, used instead of the sprite
+     */
+    #[serde(default)]
+    pub animation: Option<SpriteSheetDefinition>,
+    /**
+     * Width and height of the sprite in pixels
+     */
+    #[serde(default)]
+    pub size: f32,
+    #[serde(default = "white")]
+    pub tint: (f32, f32, f32),
+    pub health: i32,
+    #[serde(default)]
+    pub shield: i32,
+    #[serde(default)]
+    pub armor: i32,
+    /**
+     * Radius of the collider, half the sprite size if left out
+     */
+    #[serde(default)]
+    pub collider_radius: Option<f32>,
+    #[serde(default)]
+    pub points: u32,
+    /**
+     * Damage dealt to a player ship that runs into it, harmless to touch if left out
+     */
+    #[serde(default)]
+    pub contact_damage: Option<i32>,
+    #[serde(default)]
+    pub movement: Movement,
+    /**
+     * Swarms with other flocking enemies instead of moving on its own
+     */
+    #[serde(default)]
+    pub flocking: Option<FlockWeights>,
+    /**
+     * Bullet pattern phases, enemies with parts move to the next phase as they lose them
+     */
+    #[serde(default)]
+    pub weapon: Vec<EmitterPhase>,
+    #[serde(default)]
+    pub drops: Option<DropTable>,
+    #[serde(default)]
+    pub split: Option<Split>,
+    #[serde(default)]
+    pub hatchery: Option<HatcheryDefinition>,
+    /**
+     * Seconds between globs of web, if it spits any
+     */
+    #[serde(default)]
+    pub web_spitter: Option<f32>,
+    #[serde(default)]
+    pub dive: Option<DiveDefinition>,
+    /**
+     * States that drive its movement and weapon, see StateMachine
+     */
+    #[serde(default)]
+    pub behaviour: Option<BehaviourDefinition>,
+    /**
+     * Destructible parts, an enemy with parts is spawned as a boss, see spawn_boss
+     */
+    #[serde(default)]
+    pub parts: Vec<PartDefinition>,
+    /**
+     * Relative chance of being picked for a random wave, 0 keeps it out of them
+     */
+    #[serde(default)]
+    pub wave_weight: u32,
+}
+
+impl EnemyDefinition {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.tint.0, self.tint.1, self.tint.2)
+    }
+
+    pub fn collider_radius(&self) -> f32 {
+        self.collider_radius.unwrap_or(self.size / 2.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HatcheryDefinition {
+    /**
+     * Seconds between hatches
+     */
+    pub interval: f32,
+    /**
+     * Most hatchlings that can be alive at once
+     */
+    pub cap: usize,
+    pub hatchling: EnemyType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiveDefinition {
+    /**
+     * Seconds after spawning before the dive starts
+     */
+    pub interval: f32,
+    /**
+     * Speed of the dive in pixels per second
+     */
+    pub speed: f32,
+    /**
+     * Seconds of warning before the dive
+     */
+    pub telegraph: f32,
+}
+
+/**
+ * A destructible part of a boss, see BossPart
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartDefinition {
+    pub kind: BossPartKind,
+    pub sprite: String,
+    /**
+     * Position relative to the boss
+     */
+    pub offset: Vec2,
+    pub size: f32,
+    #[serde(default = "white")]
+    pub tint: (f32, f32, f32),
+    #[serde(default)]
+    pub flip_x: bool,
+    pub health: i32,
+    #[serde(default)]
+    pub shield: i32,
+    #[serde(default)]
+    pub armor: i32,
+    #[serde(default)]
+    pub points: u32,
+}
+
+impl PartDefinition {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.tint.0, self.tint.1, self.tint.2)
+    }
+}
+
+/**
+ * Every enemy archetype by id
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnemyDefinitions {
+    pub enemies: HashMap<EnemyType, EnemyDefinition>,
+    /**
+     * Texture atlases of the enemies with a sprite sheet, see load_enemy_sheets
+     */
+    #[serde(skip)]
+    pub atlases: HashMap<EnemyType, Handle<TextureAtlas>>,
+}
+
+impl EnemyDefinitions {
+    /**
+     * Reads the enemy definitions from a data file, falling back to the ones built into the game
+     * if it is missing or invalid
+     */
+    pub fn load_or_default(path: &str) -> Self {
+        let result = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str::<Self>(&contents).map_err(|err| err.to_string()));
+        let definitions: Self = match result {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                warn!(
+                    "Could not load enemy definitions from {}, using the built in ones: {}",
+                    path, err
+                );
+                ron::from_str(include_str!("../../assets/data/enemies.ron"))
+                    .expect("built in enemy definitions are invalid")
+            }
+        };
+        definitions.without_unknown_splits()
+    }
+
+    /**
+     * Drops splits into enemies that aren't defined. They would never spawn, but the stage
+     * would still count them and wait for them to die forever
+     */
+    fn without_unknown_splits(mut self) -> Self {
+        let known: HashSet<EnemyType> = self.enemies.keys().cloned().collect();
+        for (enemy_type, definition) in self.enemies.iter_mut() {
+            if let Some(split) = &definition.split {
+                if !known.contains(&split.into) {
+                    warn!(
+                        "{:?} splits into {:?} which is not defined, it won't split",
+                        enemy_type, split.into
+                    );
+                    definition.split = None;
+                }
+            }
+        }
+        self
+    }
+
+    pub fn get(&self, enemy_type: &EnemyType) -> Option<&EnemyDefinition> {
+        self.enemies.get(enemy_type)
+    }
+
+    pub fn atlas(&self, enemy_type: &EnemyType) -> Option<Handle<TextureAtlas>> {
+        self.atlases.get(enemy_type).cloned()
+    }
+
+    /**
+     * Everything an enemy breaks into when it dies, splitting elites break into copies of
+     * themselves on top of what their type splits into
+     */
+    pub fn splits(&self, enemy_type: &EnemyType, modifiers: &[EliteModifier]) -> Vec<Split> {
+        let mut splits: Vec<Split> = self
+            .get(enemy_type)
+            .and_then(|definition| definition.split.clone())
+            .into_iter()
+            .collect();
+        if modifiers.contains(&EliteModifier::Splitting) {
+            splits.push(Elite::split(enemy_type));
+        }
+        splits
+    }
+
+    /**
+     * Whether an enemy hatches others, used to track egg sacs in a stage
+     */
+    pub fn hatches(&self, enemy_type: &EnemyType) -> bool {
+        self.get(enemy_type)
+            .map_or(false, |definition| definition.hatchery.is_some())
+    }
+}
+
+/**
+ * Cuts the sprite sheets of every enemy into texture atlases once at startup
+ */
+pub fn load_enemy_sheets(
+    mut definitions: ResMut<EnemyDefinitions>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let atlases: HashMap<EnemyType, Handle<TextureAtlas>> = definitions
+        .enemies
+        .iter()
+        .filter_map(|(enemy_type, definition)| {
+            let sheet = definition.animation.as_ref()?;
+            Some((
+                enemy_type.clone(),
+                texture_atlases.add(sheet.atlas(&asset_server)),
+            ))
+        })
+        .collect();
+    definitions.atlases = atlases;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn built_in() -> EnemyDefinitions {
+        ron::from_str(include_str!("../../assets/data/enemies.ron"))
+            .expect("built in enemy definitions are invalid")
+    }
+
+    #[test]
+    fn sprite_sheets_have_every_clip() {
+        for (enemy_type, definition) in built_in().enemies {
+            let sheet = match definition.animation {
+                Some(sheet) => sheet,
+                None => continue,
+            };
+            for clip in [WALK_CLIP, ATTACK_CLIP, HURT_CLIP, DIE_CLIP] {
+                assert!(
+                    sheet.clips.contains_key(clip),
+                    "{:?} has no {} clip",
+                    enemy_type,
+                    clip
+                );
+            }
+            assert!(sheet.clips.contains_key(&sheet.default_clip));
+            let frames = sheet.columns * sheet.rows;
+            for (name, clip) in &sheet.clips {
+                assert!(
+                    clip.frames.iter().all(|&frame| frame < frames),
+                    "{:?} has frames past the end of its sheet in {}",
+                    enemy_type,
+                    name
+                );
+            }
+            let texture = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("assets")
+                .join(&sheet.texture);
+            assert!(texture.exists(), "{:?} is missing", texture);
+        }
+    }
+
+    #[test]
+    fn spiders_are_animated() {
+        let definitions = built_in();
+        for enemy_type in ["tiny_spider", "small_spider", "medium_spider"] {
+            let definition = definitions
+                .get(&EnemyType::new(enemy_type))
+                .expect("the spider is defined");
+            assert!(
+                definition.animation.is_some(),
+                "{} is not animated",
+                enemy_type
+            );
+        }
+    }
+}
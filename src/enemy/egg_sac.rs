@@ -0,0 +1,104 @@
+use std::f32::consts::TAU;
+
+use rand::{thread_rng, Rng};
+
+use crate::prelude::*;
+
+/**
+ * Periodically hatches spiders until the enemy is destroyed
+ */
+#[derive(Component)]
+pub struct Hatchery {
+    pub interval: Timer,
+    /**
+     * Most hatchlings of this sac that can be alive at once
+     */
+    pub cap: usize,
+    /**
+     * Seconds before each hatch that the sac pulses as a warning
+     */
+    pub pulse: f32,
+    pub hatchling: EnemyType,
+}
+
+impl Hatchery {
+    pub fn new(interval: f32, cap: usize, hatchling: EnemyType) -> Self {
+        Self {
+            interval: Timer::from_seconds(interval, true),
+            cap,
+            pulse: 0.6,
+            hatchling,
+        }
+    }
+}
+
+/**
+ * A spider that hatched from a sac, used to keep each sac under its cap
+ */
+#[derive(Component)]
+pub struct Hatchling {
+    pub sac: Entity,
+}
+
+/**
+ * Sent for every spider hatched, hatchlings don't go through SpawnEnemyEvent
+ */
+pub struct OnHatchEvent {
+    pub sac: Entity,
+    pub hatchling: Entity,
+}
+
+pub fn hatch_eggs(
+    mut commands: Commands,
+    time: Res<Time>,
+    definitions: Res<EnemyDefinitions>,
+    asset_server: Res<AssetServer>,
+    mut on_hatch: EventWriter<OnHatchEvent>,
+    mut sacs: Query<(Entity, &mut Hatchery, &mut Transform)>,
+    hatchlings: Query<&Hatchling>,
+) {
+    for (sac, mut hatchery, mut transform) in sacs.iter_mut() {
+        hatchery.interval.tick(time.delta());
+
+        let remaining =
+            hatchery.interval.duration().as_secs_f32() - hatchery.interval.elapsed_secs();
+        transform.scale = if remaining < hatchery.pulse {
+            Vec3::splat(1.0 + 0.15 * (remaining * 25.0).sin().abs())
+        } else {
+            Vec3::ONE
+        };
+
+        if !hatchery.interval.just_finished() {
+            continue;
+        }
+        let alive = hatchlings
+            .iter()
+            .filter(|hatchling| hatchling.sac == sac)
+            .count();
+        if alive >= hatchery.cap {
+            continue;
+        }
+
+        let angle = thread_rng().gen_range(0.0..TAU);
+        let direction = Vec2::new(angle.cos(), angle.sin());
+        let hatchling = match spawn_defined_enemy(
+            &mut commands,
+            &definitions,
+            &asset_server,
+            &hatchery.hatchling,
+            transform.translation.truncate(),
+            &[],
+        ) {
+            Some(hatchling) => hatchling,
+            None => continue,
+        };
+        commands
+            .entity(hatchling)
+            .insert(Hatchling { sac })
+            .insert(Drift {
+                velocity: direction * 150.0,
+                drag: 2.0,
+            });
+        on_hatch.send(OnHatchEvent { sac, hatchling });
+    }
+}
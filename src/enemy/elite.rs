@@ -0,0 +1,263 @@
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * Extra armor of armored elites
+ */
+const ELITE_ARMOR: i32 = 8;
+/**
+ * Speed multiplier of fast elites
+ */
+const ELITE_SPEED: f32 = 1.5;
+/**
+ * Shield of shielded elites as a fraction of their health
+ */
+const ELITE_SHIELD: f32 = 0.5;
+/**
+ * How many copies of itself a splitting elite breaks into
+ */
+const ELITE_SPLIT_COUNT: u32 = 2;
+/**
+ * Distance in pixels within which vampiric elites drain players
+ */
+const DRAIN_RADIUS: f32 = 150.0;
+const DRAIN_INTERVAL: f32 = 0.5;
+/**
+ * Damage dealt to and health gained from every player in range each drain
+ */
+const DRAIN_AMOUNT: i32 = 4;
+/**
+ * Extra score for every modifier as a fraction of the enemy's own points
+ */
+const ELITE_POINTS: f32 = 0.5;
+/**
+ * Most modifiers a rolled elite can get
+ */
+const MAX_ROLLED_MODIFIERS: usize = 3;
+pub const ELITE_AURA_TEXTURE: &str = "projectile/projectile_01.png";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EliteModifier {
+    /**
+     * Extra armor on top of whatever it already has
+     */
+    Armored,
+    /**
+     * Moves faster
+     */
+    Fast,
+    /**
+     * Breaks into copies of itself when it dies
+     */
+    Splitting,
+    /**
+     * Drains health from players close to it
+     */
+    Vampiric,
+    /**
+     * Gets a shield worth half its health
+     */
+    Shielded,
+}
+
+impl EliteModifier {
+    pub const ALL: [EliteModifier; 5] = [
+        EliteModifier::Armored,
+        EliteModifier::Fast,
+        EliteModifier::Splitting,
+        EliteModifier::Vampiric,
+        EliteModifier::Shielded,
+    ];
+
+    pub fn color(&self) -> Color {
+        match self {
+            EliteModifier::Armored => Color::rgba(0.7, 0.7, 0.75, 0.4),
+            EliteModifier::Fast => Color::rgba(1.0, 0.9, 0.2, 0.4),
+            EliteModifier::Splitting => Color::rgba(0.4, 1.0, 0.4, 0.4),
+            EliteModifier::Vampiric => Color::rgba(0.8, 0.0, 0.2, 0.4),
+            EliteModifier::Shielded => Color::rgba(0.3, 0.8, 1.0, 0.4),
+        }
+    }
+
+    /**
+     * Rolls modifiers for an enemy of a random wave, both the chance of an elite and
+     * the number of modifiers go up with difficulty
+     */
+    pub fn roll(difficulty: u32) -> Vec<EliteModifier> {
+        let mut rng = thread_rng();
+        let chance = (0.03 + 0.03 * difficulty as f64).min(0.6);
+        let mut modifiers = Vec::new();
+        while modifiers.len() < MAX_ROLLED_MODIFIERS && rng.gen_bool(chance) {
+            let remaining: Vec<EliteModifier> = Self::ALL
+                .iter()
+                .copied()
+                .filter(|modifier| !modifiers.contains(modifier))
+                .collect();
+            match remaining.choose(&mut rng) {
+                Some(modifier) => modifiers.push(*modifier),
+                None => break,
+            }
+        }
+        modifiers
+    }
+}
+
+/**
+ * An enemy with modifiers, shown by an aura around it
+ */
+#[derive(Component)]
+pub struct Elite {
+    pub modifiers: Vec<EliteModifier>,
+    /**
+     * Vampiric elites don't heal past the health they spawned with
+     */
+    pub max_health: i32,
+    pub drain: Timer,
+}
+
+impl Elite {
+    pub fn new(modifiers: Vec<EliteModifier>, max_health: i32) -> Self {
+        Self {
+            modifiers,
+            max_health,
+            drain: Timer::from_seconds(DRAIN_INTERVAL, true),
+        }
+    }
+
+    pub fn has(&self, modifier: EliteModifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+
+    pub fn speed(modifiers: &[EliteModifier]) -> f32 {
+        if modifiers.contains(&EliteModifier::Fast) {
+            ELITE_SPEED
+        } else {
+            1.0
+        }
+    }
+
+    /**
+     * Makes an enemy tougher and worth more according to its modifiers
+     */
+    pub fn apply(modifiers: &[EliteModifier], bundle: &mut GenericEnemyBundle) {
+        if modifiers.contains(&EliteModifier::Armored) {
+            bundle.armor.reduction += ELITE_ARMOR;
+        }
+        if modifiers.contains(&EliteModifier::Shielded) {
+            let max = bundle.shield.max + (bundle.health.value as f32 * ELITE_SHIELD) as i32;
+            bundle.shield = Shield::new(max);
+        }
+        let bonus = bundle.points.value as f32 * ELITE_POINTS * modifiers.len() as f32;
+        bundle.points.value += bonus as u32;
+    }
+
+    /**
+     * What a splitting elite breaks into when it dies
+     */
+    pub fn split(enemy_type: &EnemyType) -> Split {
+        Split {
+            into: enemy_type.clone(),
+            count: ELITE_SPLIT_COUNT,
+            speed: 200.0,
+        }
+    }
+}
+
+/**
+ * The glow behind an elite, cycling through the colors of its modifiers
+ */
+#[derive(Component)]
+pub struct EliteAura;
+
+#[derive(Bundle)]
+pub struct EliteAuraBundle {
+    #[bundle]
+    pub sprite: SpriteBundle,
+    pub aura: EliteAura,
+}
+
+impl EliteAuraBundle {
+    pub fn new(texture: Handle<Image>, modifiers: &[EliteModifier], size: f32) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: modifiers.first().map_or(Color::NONE, EliteModifier::color),
+                    custom_size: Some(Vec2::splat(size * 1.6)),
+                    ..Default::default()
+                },
+                texture,
+                // Behind the enemy itself
+                transform: Transform::from_xyz(0.0, 0.0, -0.05),
+                ..Default::default()
+            },
+            aura: EliteAura,
+        }
+    }
+}
+
+/**
+ * Pulses auras and cycles through the colors of elites with more than one modifier
+ */
+pub fn pulse_elite_auras(
+    time: Res<Time>,
+    elites: Query<&Elite>,
+    mut auras: Query<(&Parent, &mut Sprite, &mut Transform), With<EliteAura>>,
+) {
+    let seconds = time.seconds_since_startup() as f32;
+    for (parent, mut sprite, mut transform) in auras.iter_mut() {
+        let elite = match elites.get(parent.0) {
+            Ok(elite) => elite,
+            Err(_) => continue,
+        };
+        if elite.modifiers.is_empty() {
+            continue;
+        }
+        let index = seconds as usize % elite.modifiers.len();
+        sprite.color = elite.modifiers[index].color();
+        transform.scale = Vec3::splat(1.0 + 0.1 * (seconds * 4.0).sin());
+    }
+}
+
+/**
+ * Vampiric elites drain health from every player in range and heal by as much
+ */
+pub fn vampiric_drain(
+    time: Res<Time>,
+    mut elites: Query<(&mut Elite, &mut Health, &Transform), Without<Player>>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            &Transform,
+        ),
+        (With<Player>, Without<Invulnerable>),
+    >,
+    mut on_damage: EventWriter<OnDamageEvent>,
+) {
+    for (mut elite, mut health, transform) in elites.iter_mut() {
+        if !elite.has(EliteModifier::Vampiric) || !elite.drain.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        for (player, mut player_health, mut shield, armor, player_transform) in players.iter_mut() {
+            if player_transform.translation.truncate().distance(position) > DRAIN_RADIUS {
+                continue;
+            }
+            let damage = deal_damage(
+                &mut player_health,
+                shield.as_deref_mut(),
+                armor,
+                DRAIN_AMOUNT,
+            );
+            on_damage.send(OnDamageEvent {
+                entity: player,
+                damage,
+            });
+            health.value = (health.value + damage.health.max(0)).min(elite.max_health);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * How a bullet moves after it has been fired
+ */
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulletParams {
+    /**
+     * Initial speed in pixels per second
+     */
+    pub speed: f32,
+    /**
+     * Change in speed in pixels per second squared
+     */
+    pub acceleration: f32,
+    /**
+     * Turn rate in radians per second
+     */
+    pub angular_velocity: f32,
+    pub damage: i32,
+}
+
+impl Default for BulletParams {
+    fn default() -> Self {
+        Self {
+            speed: 200.0,
+            acceleration: 0.0,
+            angular_velocity: 0.0,
+            damage: 10,
+        }
+    }
+}
+
+/**
+ * The shape of a single volley of bullets
+ */
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum BulletPattern {
+    /**
+     * `count` bullets spread evenly around a full circle
+     */
+    Radial { count: u32 },
+    /**
+     * `arms` bullets spread around a circle, turned further by `step` radians every volley
+     */
+    Spiral { arms: u32, step: f32 },
+    /**
+     * `count` bullets spread over `spread` radians, centred on the player the enemy targets, see EnemyTarget
+     */
+    AimedFan { count: u32, spread: f32 },
+    /**
+     * `count` bullets spread over `spread` radians, swinging `amplitude` radians
+     * left and right of straight down `frequency` times per second
+     */
+    Wave {
+        count: u32,
+        spread: f32,
+        amplitude: f32,
+        frequency: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum EmitterAction {
+    Fire(BulletPattern, BulletParams),
+    Wait(f32),
+    /**
+     * Warn about the next volley for some seconds, see Telegraph
+     */
+    Telegraph(TelegraphKind, f32),
+}
+
+/**
+ * A looping sequence of volleys, a boss can have several of these
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EmitterPhase {
+    actions: Vec<EmitterAction>,
+}
+
+/**
+ * Fires bullet patterns from an enemy, looping through the actions of its current phase
+ */
+#[derive(Component)]
+pub struct BulletEmitter {
+    pub phases: Vec<EmitterPhase>,
+    /**
+     * Whether the emitter goes through its actions at all, see WeaponChoice
+     */
+    pub firing: bool,
+    phase: usize,
+    cursor: usize,
+    timer: Timer,
+    /**
+     * Current rotation of spiral patterns
+     */
+    spin: f32,
+    elapsed: f32,
+}
+
+impl BulletEmitter {
+    pub fn new(phases: Vec<EmitterPhase>) -> Self {
+        Self {
+            phases,
+            firing: true,
+            phase: 0,
+            cursor: 0,
+            timer: Timer::default(),
+            spin: 0.0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    /**
+     * Switch to another phase, starting it from its first action
+     */
+    pub fn set_phase(&mut self, phase: usize) {
+        let phase = phase.min(self.phases.len().saturating_sub(1));
+        if phase != self.phase {
+            self.phase = phase;
+            self.cursor = 0;
+            self.timer = Timer::default();
+        }
+    }
+}
+
+pub fn fire_emitters(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    time: Res<Time>,
+    sprite_assets: Res<SpriteAssets>,
+    mut emitters: Query<(
+        &mut BulletEmitter,
+        &Transform,
+        Option<&EnemyTarget>,
+        Option<&mut Telegraph>,
+    )>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for (mut emitter, transform, target, mut telegraph) in emitters.iter_mut() {
+        if !emitter.firing {
+            continue;
+        }
+        emitter.elapsed += time.delta_seconds();
+        if !emitter.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let actions = match emitter.phases.get(emitter.phase) {
+            Some(phase) if !phase.actions.is_empty() => phase.actions.clone(),
+            _ => continue,
+        };
+
+        let position = transform.translation.truncate();
+        let aim = target
+            .and_then(|target| target.player)
+            .and_then(|player| players.get(player).ok())
+            .map(|player| player.translation.truncate() - position)
+            .map(|offset| offset.y.atan2(offset.x))
+            .unwrap_or(-FRAC_PI_2);
+
+        // Each action runs at most once per frame so phases without waits can't lock up the game
+        for _ in 0..actions.len() {
+            let action = actions[emitter.cursor % actions.len()];
+            emitter.cursor = (emitter.cursor + 1) % actions.len();
+
+            match action {
+                EmitterAction::Fire(pattern, params) => {
+                    for angle in bullet_angles(&pattern, emitter.spin, emitter.elapsed, aim) {
+                        let velocity = Vec2::new(angle.cos(), angle.sin()) * params.speed;
+                        let bullet = pool.spawn(
+                            &mut commands,
+                            ProjectileBundle::enemy_bullet(
+                                sprite_assets.projectile.clone(),
+                                position,
+                                velocity,
+                                params,
+                            ),
+                        );
+                        if let Some(bullet) = bullet {
+                            if params.acceleration != 0.0 || params.angular_velocity != 0.0 {
+                                commands.entity(bullet).insert(Steering::Accelerate {
+                                    acceleration: params.acceleration,
+                                    angular_velocity: params.angular_velocity,
+                                });
+                            }
+                        }
+                    }
+                    if let BulletPattern::Spiral { step, .. } = pattern {
+                        emitter.spin = (emitter.spin + step) % TAU;
+                    }
+                }
+                EmitterAction::Wait(seconds) => {
+                    emitter.timer = Timer::from_seconds(seconds, false);
+                    break;
+                }
+                EmitterAction::Telegraph(kind, seconds) => {
+                    if let Some(telegraph) = telegraph.as_deref_mut() {
+                        telegraph.start(kind, seconds, TelegraphedAttack::Volley);
+                    }
+                    emitter.timer = Timer::from_seconds(seconds, false);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Directions in radians of every bullet in a single volley
+ */
+fn bullet_angles(pattern: &BulletPattern, spin: f32, elapsed: f32, aim: f32) -> Vec<f32> {
+    match *pattern {
+        BulletPattern::Radial { count } => circle(count, 0.0),
+        BulletPattern::Spiral { arms, .. } => circle(arms, spin),
+        BulletPattern::AimedFan { count, spread } => fan(aim, count, spread),
+        BulletPattern::Wave {
+            count,
+            spread,
+            amplitude,
+            frequency,
+        } => fan(
+            -FRAC_PI_2 + amplitude * (TAU * frequency * elapsed).sin(),
+            count,
+            spread,
+        ),
+    }
+}
+
+fn circle(count: u32, offset: f32) -> Vec<f32> {
+    (0..count)
+        .map(|i| offset + TAU * i as f32 / count as f32)
+        .collect()
+}
+
+fn fan(centre: f32, count: u32, spread: f32) -> Vec<f32> {
+    if count <= 1 {
+        return vec![centre];
+    }
+    (0..count)
+        .map(|i| centre - spread / 2.0 + spread * i as f32 / (count - 1) as f32)
+        .collect()
+}
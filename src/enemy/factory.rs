@@ -1,94 +1,194 @@
-use bevy_asset_loader::AssetCollection;
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
+/**
+ * Everything an enemy needs apart from its sprite, which is either a plain sprite or
+ * an animated sprite sheet, see spawn_defined_enemy
+ */
 #[derive(Bundle)]
 pub struct GenericEnemyBundle {
-    #[bundle]
-    pub sprite: SpriteBundle,
     pub enemy: Enemy,
+    pub faction: Faction,
     pub notify_death: NotifyDeath,
     pub health: Health,
+    pub shield: Shield,
+    pub armor: Armor,
+    pub hit_flash: HitFlash,
+    pub telegraph: Telegraph,
+    pub points: Points,
+    pub last_hit_by: LastHitBy,
+    pub target: EnemyTarget,
     pub rigidbody: RigidBody,
     pub collision_shape: CollisionShape,
     pub collision_layers: CollisionLayers,
+    pub offscreen: DespawnOffscreen,
 }
 
 impl Default for GenericEnemyBundle {
     fn default() -> Self {
         Self {
-            sprite: Default::default(),
             enemy: Enemy,
+            faction: Faction::Spiders,
             notify_death: NotifyDeath,
             health: Health { value: 1 },
+            shield: Shield::new(0),
+            armor: Armor { reduction: 0 },
+            hit_flash: HitFlash::default(),
+            telegraph: Telegraph::default(),
+            points: Points { value: 0 },
+            last_hit_by: LastHitBy::default(),
+            target: EnemyTarget::default(),
             rigidbody: RigidBody::KinematicPositionBased,
             collision_shape: CollisionShape::Sphere { radius: 1.0 },
             collision_layers: CollisionLayers::none()
                 .with_group(CollisionLayer::Enemy)
                 .with_masks(&[CollisionLayer::Player]),
+            offscreen: DespawnOffscreen::new(64.0),
         }
     }
 }
 
-#[derive(AssetCollection)]
-pub struct EnemyFactory {
-    #[asset(path = "enemy/spider_medium.png")]
-    pub spider_medium: Handle<Image>,
-    #[asset(path = "enemy/spider_small.png")]
-    pub spider_small: Handle<Image>,
-    #[asset(path = "enemy/spider_tiny.png")]
-    pub spider_tiny: Handle<Image>,
+/**
+ * The spiders an enemy breaks into when it dies
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Split {
+    pub into: EnemyType,
+    pub count: u32,
+    /**
+     * Speed in pixels per second the spiders fly outwards with
+     */
+    pub speed: f32,
 }
 
-impl EnemyFactory {
-    pub fn tiny_spider(&self) -> GenericEnemyBundle {
-        GenericEnemyBundle {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(16.0, 16.0)),
-                    ..Default::default()
-                },
-                texture: self.spider_tiny.clone(),
-                transform: Transform::from_translation(Vec3::from((Vec2::ZERO, 0.0))),
-                ..Default::default()
+impl GenericEnemyBundle {
+    pub fn new(definition: &EnemyDefinition) -> Self {
+        Self {
+            health: Health {
+                value: definition.health,
+            },
+            shield: Shield::new(definition.shield),
+            armor: Armor {
+                reduction: definition.armor,
+            },
+            points: Points {
+                value: definition.points,
+            },
+            collision_shape: CollisionShape::Sphere {
+                radius: definition.collider_radius(),
             },
-            health: Health { value: 15 },
-            collision_shape: CollisionShape::Sphere { radius: 8.0 },
             ..Default::default()
         }
     }
+}
 
-    pub fn small_spider(&self) -> GenericEnemyBundle {
-        GenericEnemyBundle {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(32.0, 32.0)),
-                    ..Default::default()
-                },
-                texture: self.spider_small.clone(),
-                transform: Transform::from_translation(Vec3::from((Vec2::ZERO, 0.0))),
-                ..Default::default()
-            },
-            health: Health { value: 50 },
-            collision_shape: CollisionShape::Sphere { radius: 16.0 },
-            ..Default::default()
+/**
+ * Spawns an enemy from its definition with everything it needs to move and attack,
+ * returns None if there is no enemy with that id. Bosses ignore elite modifiers.
+ */
+pub fn spawn_defined_enemy(
+    commands: &mut Commands,
+    definitions: &EnemyDefinitions,
+    asset_server: &AssetServer,
+    enemy_type: &EnemyType,
+    position: Vec2,
+    modifiers: &[EliteModifier],
+) -> Option<Entity> {
+    let definition = match definitions.get(enemy_type) {
+        Some(definition) => definition,
+        None => {
+            error!("There is no enemy called {:?}", enemy_type);
+            return None;
         }
+    };
+    if !definition.parts.is_empty() {
+        return Some(spawn_boss(
+            commands,
+            asset_server,
+            enemy_type,
+            definition,
+            position,
+        ));
     }
 
-    pub fn medium_spider(&self) -> GenericEnemyBundle {
-        GenericEnemyBundle {
-            sprite: SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(64.0, 64.0)),
+    let transform = Transform::from_translation(Vec3::from((position, 0.0)));
+    let mut enemy = match (&definition.animation, definitions.atlas(enemy_type)) {
+        (Some(sheet), Some(texture_atlas)) => {
+            let mut enemy = commands.spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: definition.color(),
+                    custom_size: Some(Vec2::splat(definition.size)),
                     ..Default::default()
                 },
-                texture: self.spider_medium.clone(),
-                transform: Transform::from_translation(Vec3::from((Vec2::ZERO, 0.0))),
+                texture_atlas,
+                transform,
+                ..Default::default()
+            });
+            enemy.insert(sheet.animation());
+            enemy
+        }
+        _ => commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: definition.color(),
+                custom_size: Some(Vec2::splat(definition.size)),
                 ..Default::default()
             },
-            health: Health { value: 200 },
-            collision_shape: CollisionShape::Sphere { radius: 32.0 },
+            texture: asset_server.load(definition.sprite.as_str()),
+            transform,
             ..Default::default()
-        }
+        }),
+    };
+    let mut bundle = GenericEnemyBundle::new(definition);
+    Elite::apply(modifiers, &mut bundle);
+    let health = bundle.health.value;
+    enemy.insert_bundle(bundle);
+    enemy.insert(enemy_type.clone());
+    if let Some(amount) = definition.contact_damage {
+        enemy.insert(DealsContactDamage { amount });
+    }
+    let speed = Elite::speed(modifiers);
+    // Always there so attacks like dives can change how the enemy moves
+    let mut movement = EnemyMovement::new(definition.movement.clone(), position);
+    movement.speed = speed;
+    enemy.insert(movement);
+    if let Some(mut weights) = definition.flocking {
+        weights.max_speed *= speed;
+        weights.max_force *= speed;
+        enemy.insert(Flocking::new(weights));
+    }
+    if !definition.weapon.is_empty() {
+        enemy.insert(BulletEmitter::new(definition.weapon.clone()));
+    }
+    if let Some(hatchery) = &definition.hatchery {
+        enemy.insert(Hatchery::new(
+            hatchery.interval,
+            hatchery.cap,
+            hatchery.hatchling.clone(),
+        ));
+    }
+    if let Some(cooldown) = definition.web_spitter {
+        enemy.insert(WebSpitter::new(cooldown));
+    }
+    if let Some(behaviour) = &definition.behaviour {
+        enemy.insert(StateMachine::new(behaviour.clone()));
+    }
+    if let Some(dive) = &definition.dive {
+        enemy.insert(Diver::new(
+            dive.interval,
+            dive.speed * speed,
+            dive.telegraph,
+        ));
+    }
+    if !modifiers.is_empty() {
+        enemy.insert(Elite::new(modifiers.to_vec(), health));
+        enemy.with_children(|parent| {
+            parent.spawn_bundle(EliteAuraBundle::new(
+                asset_server.load(ELITE_AURA_TEXTURE),
+                modifiers,
+                definition.size,
+            ));
+        });
     }
+    Some(enemy.id())
 }
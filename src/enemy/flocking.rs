@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * Width of a spatial grid cell in pixels
+ */
+const CELL_SIZE: f32 = 64.0;
+
+/**
+ * How strongly a swarming enemy follows each steering rule, set per enemy type in its definition
+ */
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlockWeights {
+    /**
+     * Keeping away from spiders that are too close
+     */
+    pub separation: f32,
+    /**
+     * Heading the same way as the neighbours
+     */
+    pub alignment: f32,
+    /**
+     * Moving towards the centre of the neighbours
+     */
+    pub cohesion: f32,
+    /**
+     * Going after the targeted player
+     */
+    pub seek: f32,
+    /**
+     * Distance in pixels within which other spiders count as neighbours
+     */
+    pub neighbour_radius: f32,
+    /**
+     * Distance in pixels spiders try to keep from each other
+     */
+    pub separation_radius: f32,
+    /**
+     * Top speed in pixels per second
+     */
+    pub max_speed: f32,
+    /**
+     * Largest change in velocity in pixels per second squared
+     */
+    pub max_force: f32,
+}
+
+impl Default for FlockWeights {
+    fn default() -> Self {
+        Self {
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 0.8,
+            seek: 1.0,
+            neighbour_radius: 64.0,
+            separation_radius: 20.0,
+            max_speed: 180.0,
+            max_force: 400.0,
+        }
+    }
+}
+
+/**
+ * Steers an enemy as part of a swarm, every enemy with this component flocks with the others
+ */
+#[derive(Component)]
+pub struct Flocking {
+    pub weights: FlockWeights,
+    /**
+     * Velocity in pixels per second
+     */
+    pub velocity: Vec2,
+}
+
+impl Flocking {
+    pub fn new(weights: FlockWeights) -> Self {
+        Self {
+            weights,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+/**
+ * Buckets positions into square cells so neighbours can be found without checking every pair
+ */
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: CELL_SIZE,
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl SpatialGrid {
+    fn cell(&self, position: Vec2) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /**
+     * Empties every cell while keeping their allocations around for the next frame
+     */
+    pub fn clear(&mut self) {
+        self.cells.values_mut().for_each(Vec::clear);
+    }
+
+    pub fn insert(&mut self, position: Vec2, index: usize) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    /**
+     * Everything in the cells touching a circle, callers still need to check the distance
+     */
+    pub fn nearby(&self, position: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = self.cell(position);
+        let reach = (radius / self.cell_size).ceil() as i32;
+        (x - reach..=x + reach)
+            .flat_map(move |cx| (y - reach..=y + reach).map(move |cy| (cx, cy)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flat_map(|indices| indices.iter().copied())
+    }
+}
+
+/**
+ * Separation, alignment and cohesion within the swarm plus seeking the targeted player
+ */
+pub fn flock(
+    time: Res<Time>,
+    mut grid: Local<SpatialGrid>,
+    mut boids: Query<(Entity, &mut Flocking, &mut Transform, &EnemyTarget)>,
+    players: Query<&Transform, (With<Player>, Without<Flocking>)>,
+) {
+    let snapshot: Vec<(Entity, Vec2, Vec2)> = boids
+        .iter()
+        .map(|(entity, flocking, transform, _)| {
+            (entity, transform.translation.truncate(), flocking.velocity)
+        })
+        .collect();
+    grid.clear();
+    for (index, (_, position, _)) in snapshot.iter().enumerate() {
+        grid.insert(*position, index);
+    }
+
+    let delta = time.delta_seconds();
+    for (index, (entity, position, velocity)) in snapshot.iter().enumerate() {
+        let (_, mut flocking, mut transform, target) = match boids.get_mut(*entity) {
+            Ok(boid) => boid,
+            Err(_) => continue,
+        };
+        let weights = flocking.weights;
+        // Steering force towards travelling at full speed in a direction
+        let steer = |direction: Vec2| {
+            if direction == Vec2::ZERO {
+                Vec2::ZERO
+            } else {
+                direction.normalize() * weights.max_speed - *velocity
+            }
+        };
+
+        let mut separation = Vec2::ZERO;
+        let mut heading = Vec2::ZERO;
+        let mut centre = Vec2::ZERO;
+        let mut neighbours = 0;
+        for other in grid.nearby(*position, weights.neighbour_radius) {
+            if other == index {
+                continue;
+            }
+            let (_, other_position, other_velocity) = snapshot[other];
+            let offset = *position - other_position;
+            let distance = offset.length();
+            if distance > weights.neighbour_radius {
+                continue;
+            }
+            heading += other_velocity;
+            centre += other_position;
+            neighbours += 1;
+            if distance < weights.separation_radius {
+                // Spiders on top of each other push apart in an arbitrary direction
+                let away = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    Vec2::X
+                };
+                separation += away * (1.0 - distance / weights.separation_radius);
+            }
+        }
+
+        let mut force = steer(separation) * weights.separation;
+        if neighbours > 0 {
+            force += steer(heading) * weights.alignment;
+            force += steer(centre / neighbours as f32 - *position) * weights.cohesion;
+        }
+        if let Some(player) = target.player.and_then(|player| players.get(player).ok()) {
+            force += steer(player.translation.truncate() - *position) * weights.seek;
+        }
+
+        flocking.velocity = (*velocity + force.clamp_length_max(weights.max_force) * delta)
+            .clamp_length_max(weights.max_speed);
+        transform.translation += Vec3::from((flocking.velocity, 0.0)) * delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nearby(grid: &SpatialGrid, position: Vec2, radius: f32) -> Vec<usize> {
+        let mut found: Vec<usize> = grid.nearby(position, radius).collect();
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn inserted_positions_are_nearby() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Vec2::new(10.0, 10.0), 0);
+        grid.insert(Vec2::new(20.0, 30.0), 1);
+
+        assert_eq!(nearby(&grid, Vec2::new(15.0, 15.0), 10.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn neighbours_are_found_across_cell_borders() {
+        let mut grid = SpatialGrid::default();
+        // Either side of the border between the first two cells, on both axes
+        grid.insert(Vec2::new(CELL_SIZE - 1.0, 0.0), 0);
+        grid.insert(Vec2::new(CELL_SIZE + 1.0, 0.0), 1);
+        grid.insert(Vec2::new(CELL_SIZE - 1.0, CELL_SIZE + 1.0), 2);
+        assert_ne!(
+            grid.cell(Vec2::new(CELL_SIZE - 1.0, 0.0)),
+            grid.cell(Vec2::new(CELL_SIZE + 1.0, 0.0))
+        );
+
+        assert_eq!(
+            nearby(&grid, Vec2::new(CELL_SIZE - 1.0, 0.0), 4.0),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            nearby(&grid, Vec2::new(CELL_SIZE + 1.0, 0.0), 4.0),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn neighbours_are_found_across_the_origin() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Vec2::new(-1.0, -1.0), 0);
+        grid.insert(Vec2::new(1.0, 1.0), 1);
+        assert_eq!(grid.cell(Vec2::new(-1.0, -1.0)), (-1, -1));
+        assert_eq!(grid.cell(Vec2::new(1.0, 1.0)), (0, 0));
+
+        assert_eq!(nearby(&grid, Vec2::new(-1.0, -1.0), 4.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn far_positions_are_left_out() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Vec2::ZERO, 0);
+        grid.insert(Vec2::splat(CELL_SIZE * 5.0), 1);
+
+        assert_eq!(nearby(&grid, Vec2::ZERO, CELL_SIZE), vec![0]);
+        // A wider radius reaches further cells
+        assert_eq!(nearby(&grid, Vec2::ZERO, CELL_SIZE * 5.0), vec![0, 1]);
+    }
+
+    #[test]
+    fn clear_empties_every_cell() {
+        let mut grid = SpatialGrid::default();
+        grid.insert(Vec2::ZERO, 0);
+        grid.insert(Vec2::splat(CELL_SIZE * 2.0), 1);
+        grid.clear();
+
+        assert!(nearby(&grid, Vec2::ZERO, CELL_SIZE * 4.0).is_empty());
+    }
+}
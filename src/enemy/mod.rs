@@ -1,32 +1,107 @@
+mod behaviour;
+mod boss;
+mod clips;
+mod definition;
+mod egg_sac;
+mod elite;
+mod emitter;
 mod factory;
+mod flocking;
+mod movement;
+mod telegraph;
 pub mod types;
+mod web;
 
-use bevy_asset_loader::AssetCollectionApp;
+use std::f32::consts::TAU;
 
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+pub use behaviour::*;
+pub use boss::*;
+pub use clips::*;
+pub use definition::*;
+pub use egg_sac::*;
+pub use elite::*;
+pub use emitter::*;
 pub use factory::*;
+pub use flocking::*;
+pub use movement::*;
+pub use telegraph::*;
 pub use types::*;
+pub use web::*;
 
 use crate::prelude::*;
 
 #[derive(Component)]
 pub struct Enemy;
 
+/**
+ * The player ship an enemy is going after, picked at random when it spawns
+ * and again whenever that ship is destroyed
+ */
+#[derive(Component, Default)]
+pub struct EnemyTarget {
+    pub player: Option<Entity>,
+}
+
 pub struct SpawnEnemyEvent {
     pub position: Vec2,
     pub enemy_type: EnemyType,
+    /**
+     * Initial velocity in pixels per second, slowed down by Drift
+     */
+    pub velocity: Vec2,
+    pub modifiers: Vec<EliteModifier>,
+}
+
+/**
+ * Moves an enemy that was flung away, like spiders splitting off a dead one, until drag stops it
+ */
+#[derive(Component)]
+pub struct Drift {
+    /**
+     * Velocity in pixels per second
+     */
+    pub velocity: Vec2,
+    /**
+     * Fraction of the velocity lost per second
+     */
+    pub drag: f32,
 }
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.init_collection::<EnemyFactory>();
+        app.insert_resource(EnemyDefinitions::load_or_default(ENEMY_DEFINITIONS_PATH));
+        app.add_startup_system(load_enemy_sheets);
         app.add_event::<SpawnEnemyEvent>();
+        app.add_event::<OnHatchEvent>();
         app.add_system_set(
             ConditionSet::new()
                 .run_in_state(AppState::Game(Running))
                 .with_system(spawn_enemy)
                 .with_system(on_death)
+                .with_system(choose_target)
+                .with_system(drift)
+                .with_system(fire_emitters)
+                .with_system(move_enemies)
+                .with_system(flock)
+                .with_system(start_dives)
+                .with_system(update_telegraphs)
+                .with_system(update_telegraph_markers)
+                .with_system(play_hurt_clips)
+                .with_system(play_attack_clips)
+                .with_system(spawn_death_clips)
+                .with_system(run_state_machines)
+                .with_system(pulse_elite_auras)
+                .with_system(vampiric_drain)
+                .with_system(boss_part_death)
+                .with_system(hatch_eggs)
+                .with_system(spit_webs)
+                .with_system(land_web_globs)
+                .with_system(expire_webs)
+                .with_system(track_webs)
                 .into(),
         );
     }
@@ -35,25 +110,74 @@ impl Plugin for EnemyPlugin {
 pub fn spawn_enemy(
     mut commands: Commands,
     mut event: EventReader<SpawnEnemyEvent>,
-    enemy_factory: Res<EnemyFactory>,
+    definitions: Res<EnemyDefinitions>,
+    asset_server: Res<AssetServer>,
 ) {
     for spawn_enemy_event in event.iter() {
-        let mut enemy_bundle = match spawn_enemy_event.enemy_type {
-            EnemyType::TinySpider => enemy_factory.tiny_spider(),
-            EnemyType::SmallSpider => enemy_factory.small_spider(),
-            EnemyType::MediumSpider => enemy_factory.medium_spider(),
-            _ => todo!(),
-        };
-        enemy_bundle.sprite.transform =
-            Transform::from_translation(Vec3::from((spawn_enemy_event.position, 0.0)));
-        commands.spawn_bundle(enemy_bundle);
+        let enemy = spawn_defined_enemy(
+            &mut commands,
+            &definitions,
+            &asset_server,
+            &spawn_enemy_event.enemy_type,
+            spawn_enemy_event.position,
+            &spawn_enemy_event.modifiers,
+        );
+        if let Some(enemy) = enemy {
+            if spawn_enemy_event.velocity != Vec2::ZERO {
+                commands.entity(enemy).insert(Drift {
+                    velocity: spawn_enemy_event.velocity,
+                    drag: 2.0,
+                });
+            }
+        }
     }
 }
 
-pub fn on_death(mut death_event: EventReader<OnDeathEvent>, query: Query<Entity, With<Enemy>>) {
+pub fn choose_target(mut enemies: Query<&mut EnemyTarget>, players: Query<Entity, With<Player>>) {
+    let alive: Vec<Entity> = players.iter().collect();
+    for mut target in enemies.iter_mut() {
+        let valid = target
+            .player
+            .map_or(false, |player| alive.contains(&player));
+        if !valid {
+            target.player = alive.choose(&mut thread_rng()).copied();
+        }
+    }
+}
+
+/**
+ * Splits dead spiders into smaller ones flying outwards, see EnemyDefinitions::splits
+ */
+pub fn on_death(
+    mut death_event: EventReader<OnDeathEvent>,
+    mut spawn_enemy: EventWriter<SpawnEnemyEvent>,
+    definitions: Res<EnemyDefinitions>,
+) {
     for event in death_event.iter() {
-        if let entity = query.get(event.entity) {
-            // println!("Notified enemy {:?} on death", entity);
+        let enemy_type = match &event.enemy_type {
+            Some(enemy_type) => enemy_type,
+            None => continue,
+        };
+        for split in definitions.splits(enemy_type, &event.modifiers) {
+            let offset = thread_rng().gen_range(0.0..TAU);
+            for i in 0..split.count {
+                let angle = offset + TAU * i as f32 / split.count as f32;
+                let direction = Vec2::new(angle.cos(), angle.sin());
+                spawn_enemy.send(SpawnEnemyEvent {
+                    position: event.position + direction * 8.0,
+                    enemy_type: split.into.clone(),
+                    velocity: direction * split.speed,
+                    modifiers: Vec::new(),
+                });
+            }
         }
     }
 }
+
+pub fn drift(time: Res<Time>, mut query: Query<(&mut Drift, &mut Transform)>) {
+    for (mut drift, mut transform) in query.iter_mut() {
+        transform.translation += Vec3::from((drift.velocity, 0.0)) * time.delta_seconds();
+        let drag = (1.0 - drift.drag * time.delta_seconds()).max(0.0);
+        drift.velocity *= drag;
+    }
+}
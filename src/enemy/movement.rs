@@ -0,0 +1,131 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * How an enemy moves on its own, set in its definition
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Movement {
+    /**
+     * Stays where it spawned
+     */
+    Stationary,
+    /**
+     * Flies in a straight line, velocity in pixels per second
+     */
+    Linear { velocity: Vec2 },
+    /**
+     * Walks towards the targeted player, speed in pixels per second
+     */
+    Seek { speed: f32 },
+    /**
+     * Runs away from the targeted player, speed in pixels per second
+     */
+    Flee { speed: f32 },
+    /**
+     * Descends to `hover_y`, then sways `sway` pixels left and right of where it spawned,
+     * taking `period` seconds for one full sway
+     */
+    Hover {
+        hover_y: f32,
+        descent_speed: f32,
+        sway: f32,
+        period: f32,
+    },
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Movement::Stationary
+    }
+}
+
+#[derive(Component)]
+pub struct EnemyMovement {
+    pub movement: Movement,
+    /**
+     * Horizontal position the enemy sways around
+     */
+    pub anchor_x: f32,
+    /**
+     * Seconds since the enemy started swaying
+     */
+    pub sway_time: f32,
+    /**
+     * Multiplier on how fast the enemy moves, see EliteModifier::Fast
+     */
+    pub speed: f32,
+}
+
+impl EnemyMovement {
+    pub fn new(movement: Movement, position: Vec2) -> Self {
+        Self {
+            movement,
+            anchor_x: position.x,
+            sway_time: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    /**
+     * Switches to another kind of movement starting from `position`, keeping the speed multiplier
+     */
+    pub fn set(&mut self, movement: Movement, position: Vec2) {
+        *self = Self {
+            speed: self.speed,
+            ..Self::new(movement, position)
+        };
+    }
+}
+
+pub fn move_enemies(
+    time: Res<Time>,
+    mut query: Query<(&mut EnemyMovement, &mut Transform, Option<&EnemyTarget>)>,
+    players: Query<&Transform, (With<Player>, Without<EnemyMovement>)>,
+) {
+    for (mut enemy, mut transform, target) in query.iter_mut() {
+        let to_player = target
+            .and_then(|target| target.player)
+            .and_then(|player| players.get(player).ok())
+            .map(|player| (player.translation - transform.translation).truncate())
+            .unwrap_or(Vec2::ZERO)
+            .normalize_or_zero();
+        let delta = time.delta_seconds() * enemy.speed;
+        match enemy.movement {
+            Movement::Stationary => (),
+            Movement::Linear { velocity } => {
+                transform.translation += Vec3::from((velocity, 0.0)) * delta;
+            }
+            Movement::Seek { speed } => {
+                transform.translation += Vec3::from((to_player * speed, 0.0)) * delta;
+            }
+            Movement::Flee { speed } => {
+                // Without a player to run from it heads back up where it came from
+                let away = if to_player == Vec2::ZERO {
+                    Vec2::Y
+                } else {
+                    -to_player
+                };
+                transform.translation += Vec3::from((away * speed, 0.0)) * delta;
+            }
+            Movement::Hover {
+                hover_y,
+                descent_speed,
+                sway,
+                period,
+            } => {
+                if transform.translation.y > hover_y {
+                    transform.translation.y =
+                        (transform.translation.y - descent_speed * delta).max(hover_y);
+                    continue;
+                }
+                enemy.sway_time += delta;
+                transform.translation.x =
+                    enemy.anchor_x + (TAU * enemy.sway_time / period.max(0.01)).sin() * sway;
+            }
+        }
+    }
+}
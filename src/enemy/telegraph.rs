@@ -0,0 +1,295 @@
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+const TELEGRAPH_COLOR: Color = Color::rgb(1.0, 0.9, 0.2);
+/**
+ * Seconds between warning flashes
+ */
+const FLASH_INTERVAL: f32 = 0.15;
+/**
+ * How far a winding up enemy squashes down right before it attacks
+ */
+const WIND_UP_SQUASH: f32 = 0.3;
+const MARKER_SIZE: f32 = 20.0;
+/**
+ * Distance of edge markers from the edge of the screen
+ */
+const MARKER_INSET: f32 = 16.0;
+
+/**
+ * How an enemy warns the players of an attack
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TelegraphKind {
+    /**
+     * Blinks in a warning color
+     */
+    Flash,
+    /**
+     * Squashes down as if getting ready to jump
+     */
+    WindUp,
+    /**
+     * Shows a blinking marker at the nearest point on the screen, for attacks from offscreen
+     */
+    Marker,
+}
+
+/**
+ * What happens once the warning is over
+ */
+#[derive(Clone, Copy, Debug)]
+pub enum TelegraphedAttack {
+    /**
+     * The next volley of the enemy's BulletEmitter, which waits out the warning on its own
+     */
+    Volley,
+    /**
+     * Flies straight at the targeted player
+     */
+    Dive { speed: f32 },
+}
+
+struct Warning {
+    kind: TelegraphKind,
+    attack: TelegraphedAttack,
+    timer: Timer,
+    flash: Timer,
+    marker: Option<Entity>,
+}
+
+/**
+ * Warns about an attack for a while and then carries it out. Every enemy has one so attacks
+ * can be telegraphed without inserting components, see start
+ */
+#[derive(Component, Default)]
+pub struct Telegraph {
+    warning: Option<Warning>,
+}
+
+impl Telegraph {
+    /**
+     * Starts warning for `seconds`, replacing any warning in progress
+     */
+    pub fn start(&mut self, kind: TelegraphKind, seconds: f32, attack: TelegraphedAttack) {
+        self.warning = Some(Warning {
+            kind,
+            attack,
+            timer: Timer::from_seconds(seconds, false),
+            flash: Timer::from_seconds(FLASH_INTERVAL, true),
+            marker: None,
+        });
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.warning.is_some()
+    }
+}
+
+/**
+ * A warning sign shown on the screen edge for an enemy that is about to attack from offscreen
+ */
+#[derive(Component)]
+pub struct TelegraphMarker {
+    pub owner: Entity,
+}
+
+/**
+ * Dives at the targeted player every so often after telegraphing it
+ */
+#[derive(Component)]
+pub struct Diver {
+    pub cooldown: Timer,
+    /**
+     * Speed of the dive in pixels per second
+     */
+    pub speed: f32,
+    /**
+     * Seconds of warning before the dive
+     */
+    pub telegraph: f32,
+}
+
+impl Diver {
+    pub fn new(interval: f32, speed: f32, telegraph: f32) -> Self {
+        let mut cooldown = Timer::from_seconds(interval, false);
+        // Spread out the dives of spiders that spawn together
+        cooldown.tick(
+            cooldown
+                .duration()
+                .mul_f32(thread_rng().gen_range(0.0..0.5)),
+        );
+        Self {
+            cooldown,
+            speed,
+            telegraph,
+        }
+    }
+}
+
+/**
+ * Winds up a dive, or shows an edge marker if the diver is still offscreen
+ */
+pub fn start_dives(
+    mut commands: Commands,
+    time: Res<Time>,
+    bounds: Res<ScreenBounds>,
+    mut divers: Query<(Entity, &mut Diver, &mut Telegraph, &Transform)>,
+) {
+    for (entity, mut diver, mut telegraph, transform) in divers.iter_mut() {
+        if telegraph.is_active() || !diver.cooldown.tick(time.delta()).finished() {
+            continue;
+        }
+        let kind = if bounds.contains(transform.translation.truncate(), 0.0) {
+            TelegraphKind::WindUp
+        } else {
+            TelegraphKind::Marker
+        };
+        telegraph.start(
+            kind,
+            diver.telegraph,
+            TelegraphedAttack::Dive { speed: diver.speed },
+        );
+        // Spiders only dive once, they fly off screen afterwards
+        commands.entity(entity).remove::<Diver>();
+    }
+}
+
+/**
+ * Plays the warning of every active telegraph and carries out the attack once it is over
+ */
+pub fn update_telegraphs(
+    mut commands: Commands,
+    time: Res<Time>,
+    bounds: Res<ScreenBounds>,
+    sprite_assets: Res<SpriteAssets>,
+    mut telegraphs: Query<(
+        Entity,
+        &mut Telegraph,
+        &mut Transform,
+        Option<&mut HitFlash>,
+        Option<&Sprite>,
+        Option<&TextureAtlasSprite>,
+        Option<&EnemyTarget>,
+        Option<&mut EnemyMovement>,
+    )>,
+    players: Query<&Transform, (With<Player>, Without<Telegraph>)>,
+) {
+    for (entity, mut telegraph, mut transform, flash, sprite, atlas_sprite, target, movement) in
+        telegraphs.iter_mut()
+    {
+        let warning = match telegraph.warning.as_mut() {
+            Some(warning) => warning,
+            None => continue,
+        };
+        let position = transform.translation.truncate();
+        warning.timer.tick(time.delta());
+
+        match warning.kind {
+            TelegraphKind::Flash => {
+                let color = sprite
+                    .map(|sprite| sprite.color)
+                    .or_else(|| atlas_sprite.map(|sprite| sprite.color));
+                if let (Some(mut flash), Some(color)) = (flash, color) {
+                    if warning.flash.tick(time.delta()).just_finished() {
+                        flash.start(TELEGRAPH_COLOR, FLASH_INTERVAL / 2.0, color);
+                    }
+                }
+            }
+            TelegraphKind::WindUp => {
+                let squash = WIND_UP_SQUASH * warning.timer.percent();
+                transform.scale = Vec3::new(1.0 + squash / 2.0, 1.0 - squash, 1.0);
+            }
+            TelegraphKind::Marker => {
+                if warning.marker.is_none() {
+                    let marker = commands
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color: TELEGRAPH_COLOR,
+                                custom_size: Some(Vec2::splat(MARKER_SIZE)),
+                                ..Default::default()
+                            },
+                            texture: sprite_assets.projectile.clone(),
+                            transform: Transform::from_translation(Vec3::from((
+                                marker_position(&bounds, position),
+                                1.0,
+                            ))),
+                            ..Default::default()
+                        })
+                        .insert(TelegraphMarker { owner: entity })
+                        .id();
+                    warning.marker = Some(marker);
+                }
+            }
+        }
+
+        if !warning.timer.finished() {
+            continue;
+        }
+        if warning.kind == TelegraphKind::WindUp {
+            transform.scale = Vec3::ONE;
+        }
+        if let TelegraphedAttack::Dive { speed } = warning.attack {
+            let direction = target
+                .and_then(|target| target.player)
+                .and_then(|player| players.get(player).ok())
+                .map(|player| (player.translation.truncate() - position).normalize_or_zero())
+                .filter(|direction| *direction != Vec2::ZERO)
+                .unwrap_or(-Vec2::Y);
+            if let Some(mut movement) = movement {
+                movement.set(
+                    Movement::Linear {
+                        velocity: direction * speed,
+                    },
+                    position,
+                );
+            }
+            // The dive takes over from swarming
+            commands.entity(entity).remove::<Flocking>();
+        }
+        telegraph.warning = None;
+    }
+}
+
+/**
+ * Keeps edge markers next to their enemy and removes them once the warning is over
+ */
+pub fn update_telegraph_markers(
+    mut commands: Commands,
+    time: Res<Time>,
+    bounds: Res<ScreenBounds>,
+    telegraphs: Query<(&Telegraph, &Transform)>,
+    mut markers: Query<(Entity, &TelegraphMarker, &mut Transform, &mut Sprite), Without<Telegraph>>,
+) {
+    for (entity, marker, mut transform, mut sprite) in markers.iter_mut() {
+        let owner = telegraphs.get(marker.owner).ok().filter(|(telegraph, _)| {
+            telegraph
+                .warning
+                .as_ref()
+                .map_or(false, |warning| warning.marker == Some(entity))
+        });
+        let owner_position = match owner {
+            Some((_, owner_transform)) => owner_transform.translation.truncate(),
+            None => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        let position = marker_position(&bounds, owner_position);
+        transform.translation = Vec3::from((position, transform.translation.z));
+        let blink = (time.seconds_since_startup() as f32 * 12.0).sin() * 0.5 + 0.5;
+        sprite.color.set_a(0.4 + 0.6 * blink);
+    }
+}
+
+/**
+ * The point on screen closest to a position, moved in a little from the edge
+ */
+fn marker_position(bounds: &ScreenBounds, position: Vec2) -> Vec2 {
+    position.clamp(
+        bounds.min + Vec2::splat(MARKER_INSET),
+        bounds.max - Vec2::splat(MARKER_INSET),
+    )
+}
@@ -1,6 +1,15 @@
-#[derive(Clone, Copy, Debug)]
-pub enum EnemyType {
-    TinySpider,
-    SmallSpider,
-    MediumSpider,
+use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
+
+/**
+ * Id of an enemy archetype, see EnemyDefinitions
+ */
+#[derive(Component, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EnemyType(pub String);
+
+impl EnemyType {
+    pub fn new(id: &str) -> Self {
+        Self(id.to_string())
+    }
 }
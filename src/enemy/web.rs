@@ -0,0 +1,222 @@
+use rand::{thread_rng, Rng};
+
+use crate::prelude::*;
+
+/**
+ * How long a web patch stays before it falls apart
+ */
+const WEB_LIFETIME: f32 = 8.0;
+const WEB_RADIUS: f32 = 48.0;
+
+/**
+ * A patch of web that slows down player ships overlapping it, it can be shot away
+ */
+#[derive(Component)]
+pub struct WebHazard {
+    /**
+     * Multiplier applied to the movement speed of ships caught in the web
+     */
+    pub slow: f32,
+    pub lifetime: Timer,
+}
+
+/**
+ * Spits globs of web towards the enemy's target that turn into web patches where they land
+ */
+#[derive(Component)]
+pub struct WebSpitter {
+    pub cooldown: Timer,
+    /**
+     * Speed of the glob in pixels per second
+     */
+    pub speed: f32,
+}
+
+impl WebSpitter {
+    pub fn new(cooldown: f32) -> Self {
+        let mut timer = Timer::from_seconds(cooldown, true);
+        // Spread out the first volley of spiders that spawn together
+        timer.tick(timer.duration().mul_f32(thread_rng().gen_range(0.0..1.0)));
+        Self {
+            cooldown: timer,
+            speed: 300.0,
+        }
+    }
+}
+
+/**
+ * A glob of web in flight, it has no collider and only becomes a hazard once it lands
+ */
+#[derive(Component)]
+pub struct WebGlob {
+    pub destination: Vec2,
+    pub speed: f32,
+}
+
+/**
+ * The webs a player ship is currently overlapping
+ */
+#[derive(Component, Default)]
+pub struct InWeb {
+    pub webs: Vec<Entity>,
+}
+
+impl InWeb {
+    /**
+     * The speed multiplier of the stickiest web the ship is caught in
+     */
+    pub fn slow(&self, webs: &Query<&WebHazard>) -> f32 {
+        self.webs
+            .iter()
+            .filter_map(|web| webs.get(*web).ok())
+            .map(|web| web.slow)
+            .fold(1.0, f32::min)
+    }
+}
+
+#[derive(Bundle)]
+pub struct WebPatchBundle {
+    #[bundle]
+    pub sprite: SpriteBundle,
+    pub web: WebHazard,
+    pub faction: Faction,
+    pub health: Health,
+    pub rigidbody: RigidBody,
+    pub collision_shape: CollisionShape,
+    pub collision_layers: CollisionLayers,
+}
+
+impl WebPatchBundle {
+    pub fn new(texture: Handle<Image>, position: Vec2) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.9, 0.9, 0.9, 0.35),
+                    custom_size: Some(Vec2::splat(WEB_RADIUS * 2.0)),
+                    ..Default::default()
+                },
+                texture,
+                // Below ships and spiders
+                transform: Transform::from_translation(Vec3::from((position, -0.5))),
+                ..Default::default()
+            },
+            web: WebHazard {
+                slow: 0.4,
+                lifetime: Timer::from_seconds(WEB_LIFETIME, false),
+            },
+            faction: Faction::Spiders,
+            health: Health { value: 60 },
+            rigidbody: RigidBody::Sensor,
+            collision_shape: CollisionShape::Sphere { radius: WEB_RADIUS },
+            collision_layers: CollisionLayers::none()
+                .with_group(CollisionLayer::Hazard)
+                .with_masks(&[CollisionLayer::Player]),
+        }
+    }
+}
+
+pub fn spit_webs(
+    mut commands: Commands,
+    time: Res<Time>,
+    sprite_assets: Res<SpriteAssets>,
+    mut spitters: Query<(&mut WebSpitter, &Transform, &EnemyTarget)>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for (mut spitter, transform, target) in spitters.iter_mut() {
+        if !spitter.cooldown.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let destination = match target.player.and_then(|player| players.get(player).ok()) {
+            Some(player) => player.translation.truncate(),
+            None => continue,
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.9, 0.9, 0.9, 0.8),
+                    custom_size: Some(Vec2::splat(14.0)),
+                    ..Default::default()
+                },
+                texture: sprite_assets.projectile.clone(),
+                transform: Transform::from_translation(transform.translation),
+                ..Default::default()
+            })
+            .insert(WebGlob {
+                destination,
+                speed: spitter.speed,
+            });
+    }
+}
+
+/**
+ * Flies web globs to where they were aimed and lays a web patch there
+ */
+pub fn land_web_globs(
+    mut commands: Commands,
+    time: Res<Time>,
+    sprite_assets: Res<SpriteAssets>,
+    mut globs: Query<(Entity, &WebGlob, &mut Transform)>,
+) {
+    for (entity, glob, mut transform) in globs.iter_mut() {
+        let offset = glob.destination - transform.translation.truncate();
+        let step = glob.speed * time.delta_seconds();
+        if offset.length() > step {
+            transform.translation += Vec3::from((offset.normalize() * step, 0.0));
+            continue;
+        }
+        commands.entity(entity).despawn();
+        commands.spawn_bundle(WebPatchBundle::new(
+            sprite_assets.projectile.clone(),
+            glob.destination,
+        ));
+    }
+}
+
+/**
+ * Fades out webs at the end of their lifetime
+ */
+pub fn expire_webs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut webs: Query<(Entity, &mut WebHazard, &mut Sprite)>,
+) {
+    for (entity, mut web, mut sprite) in webs.iter_mut() {
+        if web.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        } else if web.lifetime.percent_left() < 0.2 {
+            sprite.color.set_a(0.35 * web.lifetime.percent_left() / 0.2);
+        }
+    }
+}
+
+/**
+ * Keeps track of which webs every ship is caught in
+ */
+pub fn track_webs(
+    mut collision_events: EventReader<CollisionEvent>,
+    webs: Query<(), With<WebHazard>>,
+    mut ships: Query<&mut InWeb>,
+) {
+    // Webs can be shot away or expire while a ship is still caught in them
+    for mut in_web in ships.iter_mut() {
+        if in_web.webs.iter().any(|web| webs.get(*web).is_err()) {
+            in_web.webs.retain(|web| webs.get(*web).is_ok());
+        }
+    }
+
+    for event in collision_events.iter() {
+        let (e1, e2) = event.rigid_body_entities();
+        for (web, ship) in [(e1, e2), (e2, e1)] {
+            if webs.get(web).is_err() {
+                continue;
+            }
+            if let Ok(mut in_web) = ships.get_mut(ship) {
+                if event.is_started() {
+                    in_web.webs.push(web);
+                } else {
+                    in_web.webs.retain(|other| *other != web);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::prelude::*;
 
 #[derive(Component)]
@@ -5,6 +7,111 @@ pub struct Health {
     pub value: i32,
 }
 
+/**
+ * Shield points that absorb damage before Health and regenerate once no damage
+ * has been taken for a while
+ */
+#[derive(Component)]
+pub struct Shield {
+    pub value: i32,
+    pub max: i32,
+    /**
+     * Restarted by every hit, regeneration begins once it finishes
+     */
+    pub regen_delay: Timer,
+    /**
+     * Shield points regained per second
+     */
+    pub regen_rate: f32,
+    /**
+     * Regenerated fractions of a shield point, added once they add up
+     */
+    pub regen_buffer: f32,
+}
+
+impl Shield {
+    pub fn new(max: i32) -> Self {
+        Self {
+            value: max,
+            max,
+            regen_delay: Timer::from_seconds(3.0, false),
+            regen_rate: 10.0,
+            regen_buffer: 0.0,
+        }
+    }
+}
+
+/**
+ * Flat damage reduction applied to every hit, a hit always deals at least one damage
+ */
+#[derive(Component)]
+pub struct Armor {
+    pub reduction: i32,
+}
+
+/**
+ * How a hit was split between an entity's shield and its health
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Damage {
+    pub shield: i32,
+    pub health: i32,
+}
+
+/**
+ * Applies armor to the damage, then takes it from the shield before the health
+ */
+pub fn deal_damage(
+    health: &mut Health,
+    shield: Option<&mut Shield>,
+    armor: Option<&Armor>,
+    amount: i32,
+) -> Damage {
+    let mut remaining = match armor {
+        Some(armor) if amount > 0 => (amount - armor.reduction).max(1),
+        _ => amount,
+    };
+    let mut damage = Damage::default();
+    if let Some(shield) = shield {
+        damage.shield = remaining.min(shield.value).max(0);
+        shield.value -= damage.shield;
+        shield.regen_delay.reset();
+        remaining -= damage.shield;
+    }
+    damage.health = remaining;
+    health.value -= remaining;
+    damage
+}
+
+pub const SHIELD_HIT_COLOR: Color = Color::rgb(0.3, 0.8, 1.0);
+pub const HEALTH_HIT_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+
+/**
+ * Tints the sprite for a moment after a hit, in a different color for shield and health hits
+ */
+#[derive(Component, Default)]
+pub struct HitFlash {
+    pub timer: Timer,
+    pub color: Color,
+    /**
+     * The sprite's own color while it is flashing, restored afterwards
+     */
+    base: Option<Color>,
+}
+
+impl HitFlash {
+    /**
+     * Tints the sprite with `color` for `seconds`, `current` is the color of the sprite right now
+     */
+    pub fn start(&mut self, color: Color, seconds: f32, current: Color) {
+        if self.base.is_none() {
+            self.base = Some(current);
+        }
+        self.color = color;
+        self.timer = Timer::from_seconds(seconds, false);
+    }
+}
+
 #[derive(Component)]
 pub struct DealsContactDamage {
     pub amount: i32,
@@ -13,8 +120,64 @@ pub struct DealsContactDamage {
 #[derive(Component)]
 pub struct NotifyDeath;
 
+/**
+ * The player that last damaged this entity, used to credit kills
+ */
+#[derive(Component, Default)]
+pub struct LastHitBy {
+    pub player: Option<usize>,
+}
+
+/**
+ * Ignores all contact damage until the timer runs out, the sprite blinks meanwhile
+ */
+#[derive(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+    pub blink: bool,
+}
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, false),
+            blink: true,
+        }
+    }
+
+    /**
+     * Invulnerability that is already shown some other way, like the dodge roll animation
+     */
+    pub fn without_blink(seconds: f32) -> Self {
+        Self {
+            blink: false,
+            ..Self::new(seconds)
+        }
+    }
+
+    pub fn remaining_secs(&self) -> f32 {
+        self.timer.duration().as_secs_f32() - self.timer.elapsed_secs()
+    }
+}
+
+/**
+ * Sent whenever damage is dealt to an entity
+ */
+pub struct OnDamageEvent {
+    pub entity: Entity,
+    pub damage: Damage,
+}
+
 pub struct OnDeathEvent {
     pub entity: Entity,
+    /**
+     * The player credited with the kill
+     */
+    pub killer: Option<usize>,
+    pub points: u32,
+    pub position: Vec2,
+    pub enemy_type: Option<EnemyType>,
+    pub modifiers: Vec<EliteModifier>,
 }
 
 pub struct HealthPlugin;
@@ -22,11 +185,15 @@ pub struct HealthPlugin;
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<OnDeathEvent>();
+        app.add_event::<OnDamageEvent>();
         app.add_system_set(
             ConditionSet::new()
                 .run_in_state(AppState::Game(Running))
                 .with_system(death_system)
                 .with_system(contact_damage)
+                .with_system(update_invulnerable)
+                .with_system(regenerate_shields)
+                .with_system(hit_flash)
                 .into(),
         );
     }
@@ -35,18 +202,38 @@ impl Plugin for HealthPlugin {
 /**
  * Despawns any entities which Health components have reached zero
  * Optionally emits an NotifyDeath event for that entity
+ * Players lose a life instead, see player_death, and boss parts are handled by boss_part_death
  */
 pub fn death_system(
     mut commands: Commands,
-    enemies: Query<(Entity, &Health, Option<&NotifyDeath>)>,
+    enemies: Query<
+        (
+            Entity,
+            &Health,
+            &Transform,
+            Option<&NotifyDeath>,
+            Option<&LastHitBy>,
+            Option<&Points>,
+            Option<&EnemyType>,
+            Option<&Elite>,
+        ),
+        (Without<Player>, Without<BossPart>),
+    >,
     mut notify_death: EventWriter<OnDeathEvent>,
 ) {
     enemies.for_each(|enemy| {
-        let (entity, health, notify) = enemy;
+        let (entity, health, transform, notify, last_hit_by, points, enemy_type, elite) = enemy;
         if health.value <= 0 {
             commands.entity(entity).despawn_recursive();
             if let Some(_) = notify {
-                notify_death.send(OnDeathEvent { entity });
+                notify_death.send(OnDeathEvent {
+                    entity,
+                    killer: last_hit_by.and_then(|hit| hit.player),
+                    points: points.map_or(0, |points| points.value),
+                    position: transform.translation.truncate(),
+                    enemy_type: enemy_type.cloned(),
+                    modifiers: elite.map_or(Vec::new(), |elite| elite.modifiers.clone()),
+                });
             }
         }
     });
@@ -54,23 +241,120 @@ pub fn death_system(
 
 pub fn contact_damage(
     mut collision_events: EventReader<CollisionEvent>,
-    mut healthy: Query<&mut Health>,
-    damage_dealers: Query<(&Faction, &DealsContactDamage)>,
+    mut healthy: Query<
+        (
+            &mut Health,
+            &Faction,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            Option<&mut LastHitBy>,
+        ),
+        Without<Invulnerable>,
+    >,
+    damage_dealers: Query<(&Faction, &DealsContactDamage, Option<&Owner>)>,
+    mut on_damage: EventWriter<OnDamageEvent>,
 ) {
     for event in collision_events.iter().filter(|e| e.is_started()) {
         let (e1, e2) = event.rigid_body_entities();
 
-        if let Ok(mut health) = healthy.get_mut(e1) {
-            if let Ok((faction, damage)) = damage_dealers.get(e2) {
-                if let Faction::Player = faction {
-                    health.value -= damage.amount;
+        // Damage is only dealt between opposing factions
+        for (target, dealer) in [(e1, e2), (e2, e1)] {
+            if let Ok((mut health, faction, mut shield, armor, last_hit_by)) =
+                healthy.get_mut(target)
+            {
+                if let Ok((dealer_faction, damage, owner)) = damage_dealers.get(dealer) {
+                    if faction != dealer_faction {
+                        let damage =
+                            deal_damage(&mut health, shield.as_deref_mut(), armor, damage.amount);
+                        on_damage.send(OnDamageEvent {
+                            entity: target,
+                            damage,
+                        });
+                        if let (Some(mut last_hit_by), Some(owner)) = (last_hit_by, owner) {
+                            last_hit_by.player = Some(owner.player);
+                        }
+                    }
                 }
             }
-        } else if let Ok(mut health) = healthy.get_mut(e2) {
-            if let Ok((faction, damage)) = damage_dealers.get(e1) {
-                if let Faction::Player = faction {
-                    health.value -= damage.amount;
-                }
+        }
+    }
+}
+
+/**
+ * Blinks invulnerable entities and makes them vulnerable again once their time is up
+ */
+pub fn update_invulnerable(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+) {
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        if invulnerable.timer.tick(time.delta()).finished() {
+            visibility.is_visible = true;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else if invulnerable.blink {
+            visibility.is_visible = (invulnerable.timer.elapsed_secs() * 10.0) as u32 % 2 == 0;
+        }
+    }
+}
+
+pub fn regenerate_shields(time: Res<Time>, mut query: Query<&mut Shield>) {
+    for mut shield in query.iter_mut() {
+        if shield.value >= shield.max {
+            shield.regen_buffer = 0.0;
+            continue;
+        }
+        if !shield.regen_delay.tick(time.delta()).finished() {
+            continue;
+        }
+        shield.regen_buffer += shield.regen_rate * time.delta_seconds();
+        let regained = shield.regen_buffer.floor();
+        shield.regen_buffer -= regained;
+        shield.value = (shield.value + regained as i32).min(shield.max);
+    }
+}
+
+pub fn hit_flash(
+    time: Res<Time>,
+    mut on_damage: EventReader<OnDamageEvent>,
+    mut query: Query<(
+        Entity,
+        &mut HitFlash,
+        Option<&mut Sprite>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    let hits: HashMap<Entity, Color> = on_damage
+        .iter()
+        .map(|event| {
+            let color = if event.damage.health > 0 {
+                HEALTH_HIT_COLOR
+            } else {
+                SHIELD_HIT_COLOR
+            };
+            (event.entity, color)
+        })
+        .collect();
+
+    for (entity, mut flash, mut sprite, mut atlas_sprite) in query.iter_mut() {
+        let color = if let Some(sprite) = sprite.as_deref_mut() {
+            &mut sprite.color
+        } else if let Some(sprite) = atlas_sprite.as_deref_mut() {
+            &mut sprite.color
+        } else {
+            continue;
+        };
+
+        if let Some(hit_color) = hits.get(&entity) {
+            flash.start(*hit_color, 0.1, *color);
+        }
+
+        if let Some(base) = flash.base {
+            if flash.timer.tick(time.delta()).finished() {
+                *color = base;
+                flash.base = None;
+            } else {
+                *color = flash.color;
             }
         }
     }
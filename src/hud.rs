@@ -0,0 +1,122 @@
+use crate::prelude::*;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(
+            AppState::Game(Running),
+            spawn_hud.run_unless_resource_exists::<Resuming>(),
+        )
+        .add_system(update_hud.run_in_state(AppState::Game(Running)));
+    }
+}
+
+/**
+ * Score, lives, bombs, health and shield readout of a single player
+ */
+#[derive(Component)]
+pub struct PlayerHud {
+    pub player: usize,
+    /**
+     * Runs while the readout shows the color of the last hit the player took
+     */
+    pub flash: Timer,
+    pub flash_color: Color,
+}
+
+fn spawn_hud(mut commands: Commands, font_assets: Res<FontAssets>, players: Res<Players>) {
+    for (index, _) in players
+        .slots
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.joined)
+    {
+        let mut position = Rect {
+            top: Val::Px(10.0),
+            ..Default::default()
+        };
+        // Player one on the left, player two on the right
+        if index % 2 == 0 {
+            position.left = Val::Px(10.0);
+        } else {
+            position.right = Val::Px(10.0);
+        }
+
+        commands
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: font_assets.title_font.clone(),
+                        font_size: 24.0,
+                        color: PLAYER_TINTS[index],
+                    },
+                    TextAlignment::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(PlayerHud {
+                player: index,
+                flash: Timer::default(),
+                flash_color: Color::WHITE,
+            });
+    }
+}
+
+fn update_hud(
+    time: Res<Time>,
+    players: Res<Players>,
+    mut on_damage: EventReader<OnDamageEvent>,
+    ships: Query<(&Player, &Health, &Shield)>,
+    mut query: Query<(&mut PlayerHud, &mut Text)>,
+) {
+    let hits: Vec<(usize, Damage)> = on_damage
+        .iter()
+        .filter_map(|event| {
+            ships
+                .get(event.entity)
+                .ok()
+                .map(|(player, _, _)| (player.index, event.damage))
+        })
+        .collect();
+
+    for (mut hud, mut text) in query.iter_mut() {
+        let slot = &players.slots[hud.player];
+        let mut value = format!(
+            "P{} {:08}\nLIVES {}\nBOMBS {}",
+            hud.player + 1,
+            slot.score,
+            slot.lives,
+            slot.bombs
+        );
+        if let Some((_, health, shield)) =
+            ships.iter().find(|(ship, _, _)| ship.index == hud.player)
+        {
+            value += &format!("\nHP {}", health.value.max(0));
+            if shield.max > 0 {
+                value += &format!("\nSHIELD {}/{}", shield.value, shield.max);
+            }
+        }
+        text.sections[0].value = value;
+
+        if let Some((_, damage)) = hits.iter().find(|(player, _)| *player == hud.player) {
+            hud.flash_color = if damage.health > 0 {
+                HEALTH_HIT_COLOR
+            } else {
+                SHIELD_HIT_COLOR
+            };
+            hud.flash = Timer::from_seconds(0.3, false);
+        }
+        text.sections[0].style.color = if hud.flash.tick(time.delta()).finished() {
+            PLAYER_TINTS[hud.player]
+        } else {
+            hud.flash_color
+        };
+    }
+}
@@ -0,0 +1,44 @@
+mod animation;
+mod assets;
+mod collision;
+mod components;
+mod controls;
+mod enemy;
+mod health;
+mod hud;
+mod menu;
+mod offscreen;
+mod pickup;
+mod player;
+mod projectile;
+mod stage;
+mod state;
+mod util;
+mod weapons;
+
+use bevy::math::{const_vec2, Vec2};
+
+pub mod prelude {
+    pub use crate::animation::*;
+    pub use crate::assets::*;
+    pub use crate::collision::*;
+    pub use crate::components::*;
+    pub use crate::controls::*;
+    pub use crate::enemy::*;
+    pub use crate::health::*;
+    pub use crate::hud::*;
+    pub use crate::menu::*;
+    pub use crate::offscreen::*;
+    pub use crate::pickup::*;
+    pub use crate::player::*;
+    pub use crate::projectile::*;
+    pub use crate::stage::*;
+    pub use crate::state::{AppState, GameState::*, MenuState::*};
+    pub use crate::util::*;
+    pub use crate::weapons::*;
+    pub use bevy::prelude::*;
+    pub use heron::prelude::*;
+    pub use iyes_loopless::prelude::*;
+}
+
+pub const WINDOW_SIZE: Vec2 = const_vec2!([600.0, 800.0]);
@@ -0,0 +1,227 @@
+mod pause;
+mod settings;
+
+pub use pause::*;
+pub use settings::*;
+
+use crate::prelude::*;
+
+const TITLE_FONT: &str = "font/Eordeoghlakat.ttf";
+const MENU_FONT: &str = "font/FiraCode-Regular.ttf";
+
+const SELECTED_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StartMenuSelection>()
+            .add_enter_system(AppState::Menu(StartMenu), start_menu)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(AppState::Menu(StartMenu))
+                    .with_system(start_menu_controls)
+                    .with_system(highlight_start_menu)
+                    .with_system(update_join_text)
+                    .into(),
+            )
+            .add_exit_system(AppState::Menu(StartMenu), despawn_with::<StartMenuTag>)
+            .add_plugin(SettingsPlugin)
+            .add_plugin(PausePlugin);
+    }
+}
+
+#[derive(Component)]
+pub struct StartMenuTag {}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum StartMenuItem {
+    Start,
+    Settings,
+}
+
+impl StartMenuItem {
+    const ALL: [StartMenuItem; 2] = [StartMenuItem::Start, StartMenuItem::Settings];
+}
+
+/**
+ * Shows whether a player other than the first has joined
+ */
+#[derive(Component)]
+pub struct JoinText {
+    pub player: usize,
+}
+
+#[derive(Default)]
+pub struct StartMenuSelection {
+    pub index: usize,
+}
+
+fn start_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let join_style = TextStyle {
+        font: asset_server.load(MENU_FONT),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            // title text
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "ARACHNOPHOBIC",
+                    TextStyle {
+                        font: asset_server.load(TITLE_FONT),
+                        font_size: 100.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..Default::default()
+            });
+            // start and settings buttons
+            for (item, label) in [
+                (StartMenuItem::Start, "START"),
+                (StartMenuItem::Settings, "SETTINGS"),
+            ] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(300.0), Val::Px(75.0)),
+                            margin: Rect::all(Val::Auto),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: Color::NONE.into(),
+                        ..Default::default()
+                    })
+                    .insert(item)
+                    .with_children(|button| {
+                        button.spawn_bundle(TextBundle {
+                            style: Style {
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                label,
+                                TextStyle {
+                                    font: asset_server.load(MENU_FONT),
+                                    font_size: 50.0,
+                                    color: Color::WHITE,
+                                },
+                                TextAlignment::default(),
+                            ),
+                            ..Default::default()
+                        });
+                    });
+            }
+            for player in 1..MAX_PLAYERS {
+                parent
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(10.0)),
+                            ..Default::default()
+                        },
+                        text: Text::with_section("", join_style.clone(), TextAlignment::default()),
+                        ..Default::default()
+                    })
+                    .insert(JoinText { player });
+            }
+        })
+        .insert(StartMenuTag {});
+}
+
+fn start_menu_controls(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    player_actions: Res<PlayerActions>,
+    mut players: ResMut<Players>,
+    mut selection: ResMut<StartMenuSelection>,
+    buttons: Query<(&StartMenuItem, &Interaction), Changed<Interaction>>,
+) {
+    // Fire on a player's own controls joins them instead of choosing a menu item
+    for (index, slot) in players.slots.iter_mut().enumerate() {
+        if !slot.joined && player_actions.get(index).just_pressed(Action::Fire) {
+            slot.joined = true;
+            return;
+        }
+    }
+
+    let count = StartMenuItem::ALL.len();
+    if actions.just_pressed(Action::MoveUp) {
+        selection.index = (selection.index + count - 1) % count;
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        selection.index = (selection.index + 1) % count;
+    }
+
+    let mut chosen = None;
+    if actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Fire) {
+        chosen = Some(StartMenuItem::ALL[selection.index]);
+    }
+    for (item, interaction) in buttons.iter() {
+        match interaction {
+            Interaction::Clicked => chosen = Some(*item),
+            Interaction::Hovered => {
+                selection.index = StartMenuItem::ALL.iter().position(|i| i == item).unwrap()
+            }
+            Interaction::None => (),
+        }
+    }
+
+    match chosen {
+        Some(StartMenuItem::Start) => {
+            players.reset();
+            commands.insert_resource(NextState(AppState::Game(Running)));
+        }
+        Some(StartMenuItem::Settings) => {
+            commands.insert_resource(NextState(AppState::Menu(Settings)));
+        }
+        None => (),
+    }
+}
+
+fn update_join_text(players: Res<Players>, mut query: Query<(&JoinText, &mut Text)>) {
+    for (join, mut text) in query.iter_mut() {
+        let slot = &players.slots[join.player];
+        text.sections[0].value = if slot.joined {
+            format!("P{} JOINED", join.player + 1)
+        } else {
+            format!("P{} PRESS FIRE TO JOIN", join.player + 1)
+        };
+        text.sections[0].style.color = PLAYER_TINTS[join.player];
+    }
+}
+
+fn highlight_start_menu(
+    selection: Res<StartMenuSelection>,
+    buttons: Query<(&StartMenuItem, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (item, children) in buttons.iter() {
+        let selected = StartMenuItem::ALL[selection.index] == *item;
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].style.color = if selected {
+                    SELECTED_COLOR
+                } else {
+                    Color::WHITE
+                };
+            }
+        }
+    }
+}
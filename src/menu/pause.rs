@@ -0,0 +1,83 @@
+use crate::prelude::*;
+
+use super::MENU_FONT;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(toggle_pause.run_if(in_game))
+            .add_enter_system(AppState::Game(Pause), pause_menu)
+            .add_exit_system(AppState::Game(Pause), despawn_with::<PauseMenuTag>)
+            .add_enter_system(
+                AppState::Game(Running),
+                finish_resume.run_if_resource_exists::<Resuming>(),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct PauseMenuTag {}
+
+/**
+ * Present while the game goes back from paused to running.
+ * Systems that set the game up when it is entered skip while it is there.
+ */
+pub struct Resuming;
+
+fn in_game(state: Res<CurrentState<AppState>>) -> bool {
+    matches!(state.0, AppState::Game(_))
+}
+
+fn toggle_pause(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    state: Res<CurrentState<AppState>>,
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+    match state.0 {
+        AppState::Game(Running) => {
+            commands.insert_resource(NextState(AppState::Game(Pause)));
+        }
+        AppState::Game(Pause) => {
+            commands.insert_resource(Resuming);
+            commands.insert_resource(NextState(AppState::Game(Running)));
+        }
+        _ => (),
+    }
+}
+
+fn finish_resume(mut commands: Commands) {
+    commands.remove_resource::<Resuming>();
+}
+
+fn pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "PAUSED",
+                    TextStyle {
+                        font: asset_server.load(MENU_FONT),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..Default::default()
+            });
+        })
+        .insert(PauseMenuTag {});
+}
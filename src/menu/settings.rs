@@ -0,0 +1,258 @@
+use crate::prelude::*;
+
+use super::{MENU_FONT, SELECTED_COLOR};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(AppState::Menu(Settings), settings_menu)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(AppState::Menu(Settings))
+                    .with_system(settings_controls)
+                    .with_system(update_settings_text)
+                    .into(),
+            )
+            .add_exit_system(AppState::Menu(Settings), despawn_with::<SettingsMenuTag>);
+    }
+}
+
+#[derive(Component)]
+pub struct SettingsMenuTag {}
+
+/**
+ * A selectable line of the settings screen
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsItem {
+    /**
+     * Switches which player's bindings are being edited
+     */
+    Player,
+    Binding(Action),
+    Back,
+}
+
+/**
+ * Shows hints and binding conflicts
+ */
+#[derive(Component)]
+pub struct SettingsStatus;
+
+pub struct SettingsMenu {
+    pub selected: usize,
+    /**
+     * The player whose bindings are shown
+     */
+    pub player: usize,
+    /**
+     * The action waiting for a key press to be bound to
+     */
+    pub rebinding: Option<Action>,
+    pub message: String,
+}
+
+impl SettingsMenu {
+    fn items() -> Vec<SettingsItem> {
+        [SettingsItem::Player]
+            .into_iter()
+            .chain(
+                Action::REBINDABLE
+                    .iter()
+                    .map(|action| SettingsItem::Binding(*action)),
+            )
+            .chain([SettingsItem::Back])
+            .collect()
+    }
+}
+
+fn settings_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SettingsMenu {
+        selected: 0,
+        player: 0,
+        rebinding: None,
+        message: String::from("Confirm, then press a key or button to rebind"),
+    });
+
+    let text_style = TextStyle {
+        font: asset_server.load(MENU_FONT),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "SETTINGS",
+                    TextStyle {
+                        font_size: 50.0,
+                        ..text_style.clone()
+                    },
+                    TextAlignment::default(),
+                ),
+                ..Default::default()
+            });
+            for item in SettingsMenu::items() {
+                parent
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(4.0)),
+                            ..Default::default()
+                        },
+                        text: Text::with_section("", text_style.clone(), TextAlignment::default()),
+                        ..Default::default()
+                    })
+                    .insert(item);
+            }
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..text_style.clone()
+                        },
+                        TextAlignment::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(SettingsStatus);
+        })
+        .insert(SettingsMenuTag {});
+}
+
+fn settings_controls(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    actions: Res<ActionState>,
+    mut player_bindings: ResMut<PlayerBindings>,
+    mut menu: ResMut<SettingsMenu>,
+) {
+    if let Some(action) = menu.rebinding {
+        let player = menu.player;
+        let result = if let Some(key) = keyboard_input.get_just_pressed().next() {
+            Some((
+                format!("{:?}", key),
+                player_bindings.rebind_key(player, action, *key),
+            ))
+        } else if let Some(GamepadButton(_, button)) = gamepad_buttons.get_just_pressed().next() {
+            // Every player has their own gamepad, so buttons only conflict within a player
+            let result = player_bindings.players[player]
+                .rebind_button(action, *button)
+                .map_err(|other| BindingConflict {
+                    player,
+                    action: other,
+                });
+            Some((format!("{:?}", button), result))
+        } else {
+            None
+        };
+        if let Some((input, result)) = result {
+            menu.message = match result {
+                Ok(()) => format!("{} bound to {}", action.name(), input),
+                Err(conflict) if conflict.player == player => {
+                    format!("{} is already bound to {}", input, conflict.action.name())
+                }
+                Err(conflict) => format!(
+                    "{} is already bound to {} of P{}",
+                    input,
+                    conflict.action.name(),
+                    conflict.player + 1
+                ),
+            };
+            menu.rebinding = None;
+        }
+        return;
+    }
+
+    let items = SettingsMenu::items();
+    if actions.just_pressed(Action::MoveUp) {
+        menu.selected = (menu.selected + items.len() - 1) % items.len();
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        menu.selected = (menu.selected + 1) % items.len();
+    }
+
+    let back = match items[menu.selected] {
+        SettingsItem::Player if actions.just_pressed(Action::Confirm) => {
+            menu.player = (menu.player + 1) % player_bindings.players.len();
+            false
+        }
+        SettingsItem::Binding(action) if actions.just_pressed(Action::Confirm) => {
+            menu.rebinding = Some(action);
+            menu.message = format!("Press a key or button for {}", action.name());
+            false
+        }
+        SettingsItem::Back => actions.just_pressed(Action::Confirm),
+        _ => false,
+    };
+
+    if back || actions.just_pressed(Action::Back) {
+        player_bindings.save(BINDINGS_PATH);
+        commands.insert_resource(NextState(AppState::Menu(StartMenu)));
+    }
+}
+
+fn update_settings_text(
+    player_bindings: Res<PlayerBindings>,
+    menu: Res<SettingsMenu>,
+    mut items: Query<(&SettingsItem, &mut Text)>,
+    mut status: Query<&mut Text, (With<SettingsStatus>, Without<SettingsItem>)>,
+) {
+    let all_items = SettingsMenu::items();
+    let bindings = &player_bindings.players[menu.player];
+    for (item, mut text) in items.iter_mut() {
+        let selected = all_items[menu.selected] == *item;
+        text.sections[0].value = match item {
+            SettingsItem::Player => format!("{:<12}P{}", "PLAYER", menu.player + 1),
+            SettingsItem::Binding(action) if menu.rebinding == Some(*action) => {
+                format!("{:<12}...", action.name())
+            }
+            SettingsItem::Binding(action) => {
+                let inputs: Vec<String> = bindings
+                    .keys(*action)
+                    .iter()
+                    .map(|key| format!("{:?}", key))
+                    .chain(
+                        bindings
+                            .buttons(*action)
+                            .first()
+                            .map(|button| format!("{:?}", button)),
+                    )
+                    .collect();
+                format!("{:<12}{}", action.name(), inputs.join(", "))
+            }
+            SettingsItem::Back => String::from("BACK"),
+        };
+        text.sections[0].style.color = if selected {
+            SELECTED_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+    if let Ok(mut text) = status.get_single_mut() {
+        text.sections[0].value = menu.message.clone();
+    }
+}
@@ -0,0 +1,128 @@
+use crate::prelude::*;
+
+use crate::WINDOW_SIZE;
+
+pub struct OffscreenPlugin;
+
+impl Plugin for OffscreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenBounds>();
+        app.add_event::<OnDespawnOffscreenEvent>();
+        app.add_system(update_screen_bounds);
+        app.add_system(despawn_offscreen.run_in_state(AppState::Game(Running)));
+    }
+}
+
+/**
+ * The area of the world that is visible through the main camera
+ */
+pub struct ScreenBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for ScreenBounds {
+    fn default() -> Self {
+        Self {
+            min: -WINDOW_SIZE / 2.0,
+            max: WINDOW_SIZE / 2.0,
+        }
+    }
+}
+
+impl ScreenBounds {
+    /**
+     * Whether a point is on screen, or at most `margin` pixels away from it
+     */
+    pub fn contains(&self, point: Vec2, margin: f32) -> bool {
+        point.cmpge(self.min - Vec2::splat(margin)).all()
+            && point.cmple(self.max + Vec2::splat(margin)).all()
+    }
+}
+
+/**
+ * Despawns an entity once it leaves the screen by more than `margin` pixels.
+ * Entities that start offscreen are left alone until they have been on screen once.
+ */
+#[derive(Component)]
+pub struct DespawnOffscreen {
+    pub margin: f32,
+    entered: bool,
+}
+
+impl DespawnOffscreen {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            margin,
+            entered: false,
+        }
+    }
+
+    /**
+     * Checks a position against the screen bounds, returns true if the entity should be removed
+     */
+    pub fn update(&mut self, bounds: &ScreenBounds, position: Vec2) -> bool {
+        if bounds.contains(position, self.margin) {
+            self.entered = true;
+            false
+        } else {
+            self.entered
+        }
+    }
+}
+
+impl Default for DespawnOffscreen {
+    fn default() -> Self {
+        Self::new(32.0)
+    }
+}
+
+/**
+ * Sent when an entity with NotifyDeath is removed for leaving the screen
+ */
+pub struct OnDespawnOffscreenEvent {
+    pub entity: Entity,
+}
+
+pub fn update_screen_bounds(
+    mut bounds: ResMut<ScreenBounds>,
+    cameras: Query<(&OrthographicProjection, &GlobalTransform), With<MainCamera>>,
+) {
+    if let Ok((projection, transform)) = cameras.get_single() {
+        let centre = transform.translation.truncate();
+        bounds.min = centre + Vec2::new(projection.left, projection.bottom) * projection.scale;
+        bounds.max = centre + Vec2::new(projection.right, projection.top) * projection.scale;
+    }
+}
+
+/**
+ * Projectiles are culled by the projectile pool instead, see despawn_projectile
+ */
+pub fn despawn_offscreen(
+    mut commands: Commands,
+    bounds: Res<ScreenBounds>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut DespawnOffscreen,
+            Option<&NotifyDeath>,
+            Option<&Health>,
+        ),
+        Without<Projectile>,
+    >,
+    mut notify_offscreen: EventWriter<OnDespawnOffscreenEvent>,
+) {
+    for (entity, transform, mut offscreen, notify, health) in query.iter_mut() {
+        // The death system removes entities that died this frame and reports them itself
+        if health.map_or(false, |health| health.value <= 0) {
+            continue;
+        }
+        if offscreen.update(&bounds, transform.translation.truncate()) {
+            commands.entity(entity).despawn_recursive();
+            if notify.is_some() {
+                notify_offscreen.send(OnDespawnOffscreenEvent { entity });
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/**
+ * Score awarded for each score gem
+ */
+const GEM_POINTS: u32 = 100;
+/**
+ * Shield points given by a shield pickup
+ */
+const PICKUP_SHIELD: i32 = 50;
+/**
+ * Seconds each frame of the spinning pickup sheet is shown
+ */
+const SPIN_FRAME_TIME: f32 = 0.12;
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OnPickupEvent>();
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(AppState::Game(Running))
+                .with_system(spawn_drops)
+                .with_system(drift_pickups)
+                .with_system(collect_pickups)
+                .with_system(apply_pickups)
+                .into(),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PickupKind {
+    WeaponLevel,
+    ElementSwap,
+    Shield,
+    BombCharge,
+    ExtraLife,
+    ScoreGem,
+}
+
+impl PickupKind {
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::WeaponLevel => Color::rgb(1.0, 0.6, 0.1),
+            PickupKind::ElementSwap => Color::rgb(0.7, 0.3, 1.0),
+            PickupKind::Shield => Color::rgb(0.3, 0.8, 1.0),
+            PickupKind::BombCharge => Color::rgb(1.0, 1.0, 1.0),
+            PickupKind::ExtraLife => Color::rgb(0.3, 1.0, 0.4),
+            PickupKind::ScoreGem => Color::rgb(1.0, 0.9, 0.2),
+        }
+    }
+}
+
+/**
+ * What an enemy type can drop when it dies
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropTable {
+    /**
+     * Chance from 0 to 1 that anything drops at all
+     */
+    pub chance: f32,
+    /**
+     * Pickups with their relative weights, one is picked when something drops
+     */
+    pub drops: Vec<(PickupKind, u32)>,
+}
+
+impl DropTable {
+    pub fn roll(&self) -> Option<PickupKind> {
+        let mut rng = thread_rng();
+        let total: u32 = self.drops.iter().map(|(_, weight)| weight).sum();
+        if total == 0 || !rng.gen_bool(self.chance.clamp(0.0, 1.0) as f64) {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (kind, weight) in self.drops.iter() {
+            if pick < *weight {
+                return Some(*kind);
+            }
+            pick -= weight;
+        }
+        None
+    }
+}
+
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    /**
+     * Velocity in pixels per second
+     */
+    pub velocity: Vec2,
+}
+
+/**
+ * Sent when a player collects a pickup
+ */
+pub struct OnPickupEvent {
+    pub kind: PickupKind,
+    pub player: usize,
+    /**
+     * The ship that collected the pickup
+     */
+    pub ship: Entity,
+}
+
+#[derive(Bundle)]
+pub struct PickupBundle {
+    #[bundle]
+    pub sprite: SpriteSheetBundle,
+    pub animation: SpriteAnimation,
+    pub pickup: Pickup,
+    pub rigidbody: RigidBody,
+    pub collision_shape: CollisionShape,
+    pub collision_layers: CollisionLayers,
+    pub offscreen: DespawnOffscreen,
+}
+
+impl PickupBundle {
+    pub fn new(kind: PickupKind, texture_atlas: Handle<TextureAtlas>, position: Vec2) -> Self {
+        let spin = AnimationClip {
+            frames: vec![0, 1, 2, 3],
+            frame_time: SPIN_FRAME_TIME,
+            mode: AnimationMode::Loop,
+        };
+        Self {
+            sprite: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::new(20.0, 20.0)),
+                    ..Default::default()
+                },
+                texture_atlas,
+                transform: Transform::from_translation(Vec3::from((position, 0.5))),
+                ..Default::default()
+            },
+            animation: SpriteAnimation::new(HashMap::from([("spin".to_string(), spin)]), "spin"),
+            pickup: Pickup {
+                kind,
+                velocity: Vec2::new(thread_rng().gen_range(-20.0..20.0), -80.0),
+            },
+            rigidbody: RigidBody::Sensor,
+            collision_shape: CollisionShape::Sphere { radius: 12.0 },
+            collision_layers: CollisionLayers::none()
+                .with_group(CollisionLayer::Pickup)
+                .with_masks(&[CollisionLayer::Player]),
+            offscreen: DespawnOffscreen::default(),
+        }
+    }
+}
+
+/**
+ * Rolls the drop table of every enemy that died
+ */
+pub fn spawn_drops(
+    mut commands: Commands,
+    mut death_event: EventReader<OnDeathEvent>,
+    definitions: Res<EnemyDefinitions>,
+    texture_atlas_assets: Res<TextureAtlasAssets>,
+) {
+    for event in death_event.iter() {
+        let kind = event
+            .enemy_type
+            .as_ref()
+            .and_then(|enemy_type| definitions.get(enemy_type))
+            .and_then(|definition| definition.drops.as_ref())
+            .and_then(DropTable::roll);
+        if let Some(kind) = kind {
+            commands.spawn_bundle(PickupBundle::new(
+                kind,
+                texture_atlas_assets.pickup.clone(),
+                event.position,
+            ));
+        }
+    }
+}
+
+pub fn drift_pickups(time: Res<Time>, mut query: Query<(&Pickup, &mut Transform)>) {
+    for (pickup, mut transform) in query.iter_mut() {
+        transform.translation += Vec3::from((pickup.velocity, 0.0)) * time.delta_seconds();
+    }
+}
+
+pub fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut on_pickup: EventWriter<OnPickupEvent>,
+    pickups: Query<&Pickup>,
+    ships: Query<&Player>,
+) {
+    let mut collected = Vec::new();
+    for event in collision_events.iter().filter(|e| e.is_started()) {
+        let (e1, e2) = event.rigid_body_entities();
+        for (pickup_entity, ship) in [(e1, e2), (e2, e1)] {
+            if collected.contains(&pickup_entity) {
+                continue;
+            }
+            if let (Ok(pickup), Ok(player)) = (pickups.get(pickup_entity), ships.get(ship)) {
+                collected.push(pickup_entity);
+                commands.entity(pickup_entity).despawn();
+                on_pickup.send(OnPickupEvent {
+                    kind: pickup.kind,
+                    player: player.index,
+                    ship,
+                });
+            }
+        }
+    }
+}
+
+/**
+ * Applies pickups to the collecting player's lives, score, bombs, element, weapon level and shield
+ */
+pub fn apply_pickups(
+    mut on_pickup: EventReader<OnPickupEvent>,
+    mut players: ResMut<Players>,
+    weapon_levels: Res<WeaponLevels>,
+    mut elements: Query<&mut Element, With<Player>>,
+    mut weapons: Query<&mut Armed>,
+    mut shields: Query<&mut Shield, With<Player>>,
+) {
+    for event in on_pickup.iter() {
+        match event.kind {
+            PickupKind::BombCharge => players.refill_bomb(event.player),
+            PickupKind::ExtraLife => players.slots[event.player].lives += 1,
+            PickupKind::ScoreGem => players.slots[event.player].score += GEM_POINTS,
+            PickupKind::ElementSwap => {
+                if let Ok(mut element) = elements.get_mut(event.ship) {
+                    *element = element.next();
+                }
+            }
+            PickupKind::WeaponLevel => {
+                if let Ok(mut armed) = weapons.get_mut(event.ship) {
+                    let level = armed.level + 1;
+                    armed.set_level(level, &weapon_levels);
+                }
+            }
+            PickupKind::Shield => {
+                if let Ok(mut shield) = shields.get_mut(event.ship) {
+                    shield.max = shield.max.max(PICKUP_SHIELD);
+                    shield.value = shield.max;
+                }
+            }
+        }
+    }
+}
@@ -1,106 +1,626 @@
-use std::ops::Sub;
+use std::collections::HashMap;
 
 use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
-use crate::{TextureAtlasAssets, WINDOW_SIZE};
+use crate::WINDOW_SIZE;
+
+pub const MAX_PLAYERS: usize = 2;
+
+const STARTING_LIVES: u32 = 3;
+const STARTING_BOMBS: u32 = 3;
+const MAX_BOMBS: u32 = 5;
+const PLAYER_HEALTH: i32 = 100;
+/**
+ * Seconds a ship can't be hurt after respawning
+ */
+const RESPAWN_INVULNERABILITY: f32 = 2.0;
+pub const PLAYER_TINTS: [Color; MAX_PLAYERS] = [Color::WHITE, Color::rgb(1.0, 0.55, 0.55)];
+/**
+ * Radius of the ship's collider, much smaller than the 64 pixel sprite so bullets can be dodged
+ */
+pub const PLAYER_HITBOX_RADIUS: f32 = 6.0;
+/**
+ * Sideways speed in pixels per second added at random to each shot
+ */
+const SHOT_SPREAD: f32 = 45.0;
+const FOCUSED_SHOT_SPREAD: f32 = 10.0;
+/**
+ * Seconds between volleys of the main gun, as often as it used to fire at 60 frames per second
+ */
+const SHOT_INTERVAL: f32 = 1.0 / 60.0;
+/**
+ * Number of frames in the roll sheet, a dodge plays all of them once
+ */
+const ROLL_FRAMES: u8 = 11;
+/**
+ * Frame of the roll sheet with the ship level, banking left counts up from it and banking right counts down
+ */
+const LEVEL_FRAME: usize = 5;
+/**
+ * Seconds each frame of a dodge roll is shown
+ */
+const ROLL_FRAME_TIME: f32 = 0.03;
+/**
+ * Seconds each frame is shown while the ship banks or levels out
+ */
+const BANK_FRAME_TIME: f32 = 1.0 / 60.0;
+
+const LEVEL_CLIP: &str = "level";
+const BANK_LEFT_CLIP: &str = "bank_left";
+const BANK_RIGHT_CLIP: &str = "bank_right";
+const LEVEL_FROM_LEFT_CLIP: &str = "level_from_left";
+const LEVEL_FROM_RIGHT_CLIP: &str = "level_from_right";
+const ROLL_LEFT_CLIP: &str = "roll_left";
+const ROLL_RIGHT_CLIP: &str = "roll_right";
 
 #[derive(Component)]
 pub struct Player {
+    /**
+     * Which player controls this ship, starting at 0
+     */
+    pub index: usize,
     pub movement_speed: f32,
+    /**
+     * Speed while the focus action is held
+     */
+    pub focus_speed: f32,
+    pub focused: bool,
+}
+
+/**
+ * Marks the real collider of a ship, only shown while focused
+ */
+#[derive(Component)]
+pub struct HitboxDot;
+
+/**
+ * Lives and score of every player, kept apart from the ship so they outlive it
+ */
+pub struct Players {
+    pub slots: Vec<PlayerSlot>,
+}
+
+pub struct PlayerSlot {
+    pub joined: bool,
+    pub lives: u32,
+    pub score: u32,
+    /**
+     * Smart bomb charges left
+     */
+    pub bombs: u32,
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Self {
+            slots: (0..MAX_PLAYERS)
+                .map(|index| PlayerSlot {
+                    // The first player is always in the game, others join from the start menu
+                    joined: index == 0,
+                    lives: STARTING_LIVES,
+                    score: 0,
+                    bombs: STARTING_BOMBS,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Players {
+    pub fn joined(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.joined).count()
+    }
+
+    /**
+     * Restores lives and clears scores for a new game, joined players stay joined
+     */
+    pub fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            slot.lives = STARTING_LIVES;
+            slot.score = 0;
+            slot.bombs = STARTING_BOMBS;
+        }
+    }
+
+    /**
+     * Gives a player another bomb charge, used by bomb pickups
+     */
+    pub fn refill_bomb(&mut self, index: usize) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            slot.bombs = (slot.bombs + 1).min(MAX_BOMBS);
+        }
+    }
+
+    /**
+     * Where a player's ship (re)spawns, ships are spread out when playing together
+     */
+    pub fn spawn_position(&self, index: usize) -> Vec3 {
+        let offset = index as f32 - (self.joined() as f32 - 1.0) / 2.0;
+        Vec3::new(offset * 200.0, -300.0, 0.0)
+    }
 }
 
-pub struct Hardpoint {}
+/**
+ * A point on the ship the main gun fires from
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hardpoint {
+    /**
+     * Position relative to the ship in pixels
+     */
+    pub offset: Vec2,
+    /**
+     * Degrees away from straight ahead, positive angles turn left
+     */
+    pub angle: f32,
+    pub damage: i32,
+    /**
+     * Projectile speed in pixels per second
+     */
+    pub speed: f32,
+}
 
 #[derive(Component)]
 pub struct Armed {
+    /**
+     * Weapon level starting at 1, see WeaponLevels
+     */
+    pub level: usize,
     pub hardpoints: Vec<Hardpoint>,
+    /**
+     * Fire from one random hardpoint per shot instead of all of them
+     */
     pub rolling_fire: bool,
+    /**
+     * Time until the next volley, so the fire rate doesn't depend on the frame rate
+     */
+    pub cooldown: Timer,
+}
+
+impl Armed {
+    pub fn new(level: usize, weapon_levels: &WeaponLevels) -> Self {
+        let mut armed = Self {
+            level: 0,
+            hardpoints: Vec::new(),
+            rolling_fire: false,
+            cooldown: Timer::from_seconds(SHOT_INTERVAL, true),
+        };
+        armed.set_level(level, weapon_levels);
+        armed
+    }
+
+    /**
+     * Changes the weapon level and takes on that level's hardpoint layout
+     */
+    pub fn set_level(&mut self, level: usize, weapon_levels: &WeaponLevels) {
+        self.level = level.clamp(1, weapon_levels.max_level().max(1));
+        if let Some(layout) = weapon_levels.layout(self.level) {
+            self.hardpoints = layout.hardpoints.clone();
+            self.rolling_fire = layout.rolling_fire;
+        }
+    }
 }
 
+/**
+ * Periodically launches homing missiles while the player is firing
+ */
 #[derive(Component)]
-pub struct PlayerAnimation {
-    pub roll_frame: i8,
-}
-
-pub fn spawn_player(mut commands: Commands, texture_atlas_assets: Res<TextureAtlasAssets>) {
-    commands
-        .spawn_bundle(SpriteSheetBundle {
-            texture_atlas: texture_atlas_assets.player_ship.clone(),
-            sprite: TextureAtlasSprite::new(5),
-            transform: Transform::from_xyz(0.0, -300.0, 0.0),
-            ..Default::default()
-        })
-        .insert(Player {
-            movement_speed: 500.0,
-        })
-        .insert(PlayerAnimation { roll_frame: 5 })
-        .insert(RigidBody::KinematicPositionBased)
-        .insert(CollisionShape::Sphere { radius: 64.0 })
-        .insert(
-            CollisionLayers::none()
-                .with_group(CollisionLayer::Player)
-                .with_masks(&[CollisionLayer::Enemy]),
-        );
+pub struct MissileLauncher {
+    pub cooldown: Timer,
 }
 
-pub fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&Player, &mut Transform, &mut PlayerAnimation)>,
-) {
-    if let Ok((ship, mut transform, mut player_animation)) = query.get_single_mut() {
-        let mut movement = Vec2::ZERO;
+/**
+ * A barrel roll that dashes the ship sideways, the ship can't be hit while the roll plays
+ */
+#[derive(Component)]
+pub struct DodgeRoll {
+    pub cooldown: Timer,
+    /**
+     * How long each frame of the roll is shown
+     */
+    pub frame_time: Timer,
+    /**
+     * Sideways speed in pixels per second during the roll
+     */
+    pub speed: f32,
+    /**
+     * -1 when rolling left, 1 when rolling right
+     */
+    pub direction: f32,
+    pub frames_left: u8,
+}
 
-        if keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up) {
-            movement.y += ship.movement_speed;
+impl Default for DodgeRoll {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(1.0, false);
+        // Ready to roll straight away
+        cooldown.tick(cooldown.duration());
+        Self {
+            cooldown,
+            frame_time: Timer::from_seconds(ROLL_FRAME_TIME, true),
+            speed: 900.0,
+            direction: 1.0,
+            frames_left: 0,
         }
-        if keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down) {
-            movement.y -= ship.movement_speed;
-        }
-        if keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left) {
-            movement.x -= ship.movement_speed;
-        }
-        if keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right) {
-            movement.x += ship.movement_speed;
+    }
+}
+
+impl DodgeRoll {
+    pub fn rolling(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.frame_time.duration().as_secs_f32() * ROLL_FRAMES as f32
+    }
+}
+
+/**
+ * Clips of the roll sheet, a full roll wraps around back to the level frame
+ */
+fn ship_animation() -> SpriteAnimation {
+    let count = ROLL_FRAMES as usize;
+    let clip = |name: &str, frames: Vec<usize>, frame_time: f32, mode: AnimationMode| {
+        (
+            name.to_string(),
+            AnimationClip {
+                frames,
+                frame_time,
+                mode,
+            },
+        )
+    };
+    let clips = HashMap::from([
+        clip(
+            LEVEL_CLIP,
+            vec![LEVEL_FRAME],
+            BANK_FRAME_TIME,
+            AnimationMode::Loop,
+        ),
+        clip(
+            BANK_LEFT_CLIP,
+            (LEVEL_FRAME..count).collect(),
+            BANK_FRAME_TIME,
+            AnimationMode::Hold,
+        ),
+        clip(
+            BANK_RIGHT_CLIP,
+            (0..=LEVEL_FRAME).rev().collect(),
+            BANK_FRAME_TIME,
+            AnimationMode::Hold,
+        ),
+        clip(
+            LEVEL_FROM_LEFT_CLIP,
+            (LEVEL_FRAME..count).rev().collect(),
+            BANK_FRAME_TIME,
+            AnimationMode::Once,
+        ),
+        clip(
+            LEVEL_FROM_RIGHT_CLIP,
+            (0..=LEVEL_FRAME).collect(),
+            BANK_FRAME_TIME,
+            AnimationMode::Once,
+        ),
+        clip(
+            ROLL_LEFT_CLIP,
+            (1..=count).map(|i| (LEVEL_FRAME + i) % count).collect(),
+            ROLL_FRAME_TIME,
+            AnimationMode::Once,
+        ),
+        clip(
+            ROLL_RIGHT_CLIP,
+            (1..=count)
+                .map(|i| (LEVEL_FRAME + count - i) % count)
+                .collect(),
+            ROLL_FRAME_TIME,
+            AnimationMode::Once,
+        ),
+    ]);
+    SpriteAnimation::new(clips, LEVEL_CLIP)
+}
+
+pub fn spawn_player(
+    mut commands: Commands,
+    texture_atlas_assets: Res<TextureAtlasAssets>,
+    weapon_levels: Res<WeaponLevels>,
+    players: Res<Players>,
+) {
+    for (index, _) in players
+        .slots
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.joined)
+    {
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: texture_atlas_assets.player_ship.clone(),
+                sprite: TextureAtlasSprite {
+                    color: PLAYER_TINTS[index],
+                    ..TextureAtlasSprite::new(LEVEL_FRAME)
+                },
+                transform: Transform::from_translation(players.spawn_position(index)),
+                ..Default::default()
+            })
+            .insert(Player {
+                index,
+                movement_speed: 500.0,
+                focus_speed: 200.0,
+                focused: false,
+            })
+            .insert(ship_animation())
+            .insert(DodgeRoll::default())
+            .insert(InWeb::default())
+            .insert(MissileLauncher {
+                cooldown: Timer::from_seconds(0.5, true),
+            })
+            .insert(Armed::new(1, &weapon_levels))
+            .insert(BeamEmitter::default())
+            .insert(Health {
+                value: PLAYER_HEALTH,
+            })
+            // Empty until a shield pickup is collected
+            .insert(Shield::new(0))
+            .insert(HitFlash::default())
+            .insert(Faction::Player)
+            .insert(Element::Water)
+            .insert(Owner { player: index })
+            .insert(RigidBody::KinematicPositionBased)
+            .insert(CollisionShape::Sphere {
+                radius: PLAYER_HITBOX_RADIUS,
+            })
+            .insert(
+                CollisionLayers::none()
+                    .with_group(CollisionLayer::Player)
+                    .with_masks(&[
+                        CollisionLayer::Enemy,
+                        CollisionLayer::EnemyProjectile,
+                        CollisionLayer::Pickup,
+                        CollisionLayer::Hazard,
+                    ]),
+            )
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgb(1.0, 0.2, 0.2),
+                            custom_size: Some(Vec2::splat(PLAYER_HITBOX_RADIUS * 2.0)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0.0, 0.0, 0.5),
+                        visibility: Visibility { is_visible: false },
+                        ..Default::default()
+                    })
+                    .insert(HitboxDot);
+            });
+    }
+}
+
+pub fn player_movement(
+    player_actions: Res<PlayerActions>,
+    time: Res<Time>,
+    webs: Query<&WebHazard>,
+    mut query: Query<(
+        &mut Player,
+        &mut Transform,
+        &mut SpriteAnimation,
+        &TextureAtlasSprite,
+        &DodgeRoll,
+        &InWeb,
+    )>,
+) {
+    for (mut ship, mut transform, mut animation, sprite, dodge, in_web) in query.iter_mut() {
+        let actions = player_actions.get(ship.index);
+        ship.focused = actions.pressed(Action::Focus);
+        // Rolling ships are moved by player_dodge
+        if dodge.rolling() {
+            continue;
         }
+        let speed = if ship.focused {
+            ship.focus_speed
+        } else {
+            ship.movement_speed
+        } * in_web.slow(&webs);
+        // Analog input scales the speed by how far the stick is pushed
+        let movement = actions.movement() * speed;
 
-        transform.translation += Vec3::from((movement, 0.0)) * crate::TIME_STEP;
+        transform.translation += Vec3::from((movement, 0.0)) * time.delta_seconds();
 
-        if movement.x < 0.0 {
-            player_animation.roll_frame = (player_animation.roll_frame + 1).min(10);
-        } else if movement.x > 0.0 {
-            player_animation.roll_frame = (player_animation.roll_frame - 1).max(0);
+        // A ship banked one way levels out before banking the other way
+        let clip = if movement.x < 0.0 && sprite.index >= LEVEL_FRAME {
+            BANK_LEFT_CLIP
+        } else if movement.x > 0.0 && sprite.index <= LEVEL_FRAME {
+            BANK_RIGHT_CLIP
+        } else if sprite.index > LEVEL_FRAME {
+            LEVEL_FROM_LEFT_CLIP
+        } else if sprite.index < LEVEL_FRAME {
+            LEVEL_FROM_RIGHT_CLIP
         } else {
-            player_animation.roll_frame -= player_animation.roll_frame.sub(5).signum();
-        }
+            LEVEL_CLIP
+        };
+        animation.play_from(clip, sprite.index);
 
         // limit movement
         limit_player_movement(&mut transform);
     }
 }
 
+/**
+ * Starts a dodge roll when the cooldown allows it and plays the roll, dashing the ship sideways
+ */
+pub fn player_dodge(
+    mut commands: Commands,
+    player_actions: Res<PlayerActions>,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &Player,
+        &mut DodgeRoll,
+        &mut Transform,
+        &mut SpriteAnimation,
+        Option<&Invulnerable>,
+    )>,
+) {
+    for (entity, player, mut dodge, mut transform, mut animation, invulnerable) in query.iter_mut()
+    {
+        dodge.cooldown.tick(time.delta());
+
+        if !dodge.rolling() {
+            let actions = player_actions.get(player.index);
+            if actions.just_pressed(Action::Dodge) && dodge.cooldown.finished() {
+                dodge.direction = if actions.movement().x < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                dodge.frames_left = ROLL_FRAMES;
+                dodge.frame_time.reset();
+                dodge.cooldown.reset();
+                animation.play(if dodge.direction < 0.0 {
+                    ROLL_LEFT_CLIP
+                } else {
+                    ROLL_RIGHT_CLIP
+                });
+                // Keep any longer invulnerability, e.g. from respawning
+                let roll_secs = dodge.duration_secs();
+                if invulnerable.map_or(true, |inv| inv.remaining_secs() < roll_secs) {
+                    commands
+                        .entity(entity)
+                        .insert(Invulnerable::without_blink(roll_secs));
+                }
+            }
+            continue;
+        }
+
+        transform.translation.x += dodge.direction * dodge.speed * time.delta_seconds();
+        limit_player_movement(&mut transform);
+
+        // The roll clip shows the frames, this only times how long the roll lasts
+        if dodge.frame_time.tick(time.delta()).just_finished() {
+            dodge.frames_left -= 1;
+        }
+    }
+}
+
 pub fn player_shoot(
-    keyboard_input: Res<Input<KeyCode>>,
+    player_actions: Res<PlayerActions>,
+    time: Res<Time>,
     mut spawn_projectile: EventWriter<SpawnProjectileEvent>,
-    query: Query<(&Player, &Transform, &PlayerAnimation)>,
+    mut query: Query<(
+        &Player,
+        &Transform,
+        &mut Armed,
+        &mut MissileLauncher,
+        &mut BeamEmitter,
+        &Element,
+    )>,
 ) {
-    if let Ok((_player, transform, _player_animation)) = query.get_single() {
-        if keyboard_input.pressed(KeyCode::Space) {
-            let offset = Vec3::new(
-                if thread_rng().gen_bool(0.5) {
-                    14.0
+    for (player, transform, mut armed, mut launcher, mut beam, element) in query.iter_mut() {
+        let actions = player_actions.get(player.index);
+        beam.firing = actions.pressed(Action::Beam);
+
+        if actions.pressed(Action::Fire) {
+            // Several volleys are due at once when a frame takes longer than the interval
+            let volleys = armed.cooldown.tick(time.delta()).times_finished();
+            let count = armed.hardpoints.len();
+            for _ in 0..volleys {
+                let firing = if armed.rolling_fire && count > 0 {
+                    let index = thread_rng().gen_range(0..count);
+                    index..index + 1
                 } else {
-                    -14.0
-                },
-                0.0,
-                0.0,
-            );
-            spawn_projectile.send(SpawnProjectileEvent {
-                position: transform.translation.clone() + offset,
-                rotation: transform.rotation.clone(),
-                speed: (1000.0 + thread_rng().gen_range(-25.0..25.0)) * crate::TIME_STEP,
-            });
+                    0..count
+                };
+                for hardpoint in armed.hardpoints[firing].iter() {
+                    spawn_projectile.send(SpawnProjectileEvent {
+                        position: transform.translation + Vec3::from((hardpoint.offset, 0.0)),
+                        rotation: transform.rotation
+                            * Quat::from_rotation_z(hardpoint.angle.to_radians()),
+                        speed: hardpoint.speed + thread_rng().gen_range(-25.0..25.0),
+                        spread: if player.focused {
+                            FOCUSED_SHOT_SPREAD
+                        } else {
+                            SHOT_SPREAD
+                        },
+                        damage: hardpoint.damage,
+                        steering: None,
+                        element: *element,
+                        owner: player.index,
+                    });
+                }
+            }
+
+            if launcher.cooldown.tick(time.delta()).just_finished() {
+                spawn_projectile.send(SpawnProjectileEvent {
+                    position: transform.translation.clone(),
+                    rotation: transform.rotation.clone(),
+                    speed: 600.0,
+                    spread: 0.0,
+                    damage: 25,
+                    steering: Some(Steering::homing(4.0)),
+                    element: *element,
+                    owner: player.index,
+                });
+            }
+        }
+    }
+}
+
+/**
+ * Takes a life from players whose ship was destroyed and respawns it if they have lives left
+ */
+pub fn player_death(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    mut query: Query<(Entity, &Player, &mut Health, &mut Transform)>,
+) {
+    for (entity, player, mut health, mut transform) in query.iter_mut() {
+        if health.value > 0 {
+            continue;
+        }
+        let slot = &mut players.slots[player.index];
+        slot.lives = slot.lives.saturating_sub(1);
+        if slot.lives > 0 {
+            // A fresh ship gets at least the starting bombs back
+            slot.bombs = slot.bombs.max(STARTING_BOMBS);
+            health.value = PLAYER_HEALTH;
+            transform.translation = players.spawn_position(player.index);
+            commands
+                .entity(entity)
+                .insert(Invulnerable::new(RESPAWN_INVULNERABILITY));
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/**
+ * Credits points to the player who dealt the killing blow
+ */
+pub fn award_points(mut death_event: EventReader<OnDeathEvent>, mut players: ResMut<Players>) {
+    for event in death_event.iter() {
+        if let Some(slot) = event
+            .killer
+            .and_then(|player| players.slots.get_mut(player))
+        {
+            slot.score += event.points;
+        }
+    }
+}
+
+/**
+ * Shows the hitbox dot of focused ships
+ */
+pub fn show_hitbox(
+    ships: Query<(&Player, &Children)>,
+    mut dots: Query<&mut Visibility, With<HitboxDot>>,
+) {
+    for (player, children) in ships.iter() {
+        for &child in children.iter() {
+            if let Ok(mut visibility) = dots.get_mut(child) {
+                visibility.is_visible = player.focused;
+            }
         }
     }
 }
@@ -115,9 +635,3 @@ pub fn limit_player_movement(transform: &mut Transform) {
             0.0,
         )));
 }
-
-pub fn animate_player(mut query: Query<(&PlayerAnimation, &mut TextureAtlasSprite)>) {
-    query.for_each_mut(|(player_animation, mut sprite)| {
-        sprite.index = player_animation.roll_frame as usize;
-    });
-}
@@ -1,16 +1,41 @@
-use bevy::math::{const_vec2, Vec3Swizzles};
+use std::{collections::HashSet, f32::consts::FRAC_PI_2};
+
+use bevy::math::Vec3Swizzles;
 use rand::{thread_rng, Rng};
 
 use crate::prelude::*;
 
-pub struct ProjectilePlugin;
+/**
+ * Projectiles that can exist at once unless the plugin is given another capacity
+ */
+pub const DEFAULT_POOL_CAPACITY: usize = 4096;
+
+pub struct ProjectilePlugin {
+    /**
+     * Size of the projectile pool, shots past it are dropped
+     */
+    pub capacity: usize,
+}
+
+impl Default for ProjectilePlugin {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_POOL_CAPACITY,
+        }
+    }
+}
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnProjectileEvent>();
+        app.insert_resource(ProjectilePool::with_capacity(self.capacity));
+        // Before anything can reuse or release a projectile that was despawned last frame
+        app.add_system_to_stage(CoreStage::PreUpdate, prune_projectile_pool);
         app.add_system_set(
             ConditionSet::new()
                 .run_in_state(AppState::Game(Running))
+                .with_system(steer_projectile)
+                .with_system(retarget_on_death)
                 .with_system(update_projectile)
                 .with_system(spawn_projectile)
                 .with_system(projectile_collision)
@@ -22,16 +47,160 @@ impl Plugin for ProjectilePlugin {
 
 #[derive(Component)]
 pub struct Projectile {
-    // Velocity in pixels per second
+    /**
+     * Velocity in pixels per second
+     */
     pub velocity: Vec2,
 }
 
+/**
+ * Changes the velocity of a projectile over its lifetime
+ */
+#[derive(Component, Clone, Copy, Debug)]
+pub enum Steering {
+    /**
+     * Turns towards the nearest enemy by at most `turn_rate` radians per second
+     */
+    Homing {
+        target: Option<Entity>,
+        turn_rate: f32,
+    },
+    /**
+     * Speeds up by `acceleration` pixels per second squared while turning `angular_velocity` radians per second
+     */
+    Accelerate {
+        acceleration: f32,
+        angular_velocity: f32,
+    },
+}
+
+impl Steering {
+    pub fn homing(turn_rate: f32) -> Self {
+        Steering::Homing {
+            target: None,
+            turn_rate,
+        }
+    }
+}
+
+/**
+ * Seconds a projectile lives at most, longer than the slowest bullet takes to cross the screen
+ */
+const PROJECTILE_LIFETIME: f32 = 10.0;
+
+/**
+ * Returns a projectile to the pool once it runs out, even if it never made it on screen
+ */
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Self(Timer::from_seconds(PROJECTILE_LIFETIME, false))
+    }
+}
+
+/**
+ * Marks a projectile that is waiting in the pool to be reused
+ */
+#[derive(Component)]
+pub struct Inactive;
+
+/**
+ * Recycles projectile entities instead of spawning and despawning one for every shot
+ */
+pub struct ProjectilePool {
+    /**
+     * Maximum amount of projectiles that can exist at once, shots past this are dropped
+     */
+    pub capacity: usize,
+    free: Vec<Entity>,
+    in_use: HashSet<Entity>,
+}
+
+impl Default for ProjectilePool {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_POOL_CAPACITY)
+    }
+}
+
+impl ProjectilePool {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            free: Vec::with_capacity(capacity),
+            in_use: HashSet::with_capacity(capacity),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.in_use.len()
+    }
+
+    /**
+     * Reuses an inactive projectile or spawns a new one if the pool isn't full yet
+     */
+    pub fn spawn(&mut self, commands: &mut Commands, bundle: ProjectileBundle) -> Option<Entity> {
+        let entity = if let Some(entity) = self.free.pop() {
+            commands
+                .entity(entity)
+                .remove::<Inactive>()
+                .remove::<Steering>()
+                .remove::<Owner>()
+                .insert_bundle(bundle);
+            entity
+        } else if self.in_use.len() < self.capacity {
+            commands.spawn_bundle(bundle).id()
+        } else {
+            return None;
+        };
+        self.in_use.insert(entity);
+        Some(entity)
+    }
+
+    /**
+     * Forgets projectiles that were despawned by something other than the pool, reusing
+     * or releasing one of those would panic
+     */
+    pub fn prune(&mut self, is_alive: impl Fn(Entity) -> bool) {
+        self.free.retain(|entity| is_alive(*entity));
+        self.in_use.retain(|entity| is_alive(*entity));
+    }
+
+    /**
+     * Hides a projectile and disables its collisions until it is reused
+     */
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        // Projectiles can be released by several systems in the same frame
+        if self.in_use.remove(&entity) {
+            commands
+                .entity(entity)
+                .insert(Inactive)
+                .insert(Visibility { is_visible: false })
+                .insert(CollisionLayers::none());
+            self.free.push(entity);
+        }
+    }
+}
+
 pub struct SpawnProjectileEvent {
     pub position: Vec3,
     pub rotation: Quat,
     pub speed: f32,
+    /**
+     * Maximum sideways speed in pixels per second, picked at random for each projectile
+     */
+    pub spread: f32,
+    pub damage: i32,
+    pub steering: Option<Steering>,
+    pub element: Element,
+    /**
+     * The player that fired the projectile
+     */
+    pub owner: usize,
 }
 // TODO: move Element and ProjectileType somewhere more sensible
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Element {
     Water,
     Fire,
@@ -39,6 +208,29 @@ pub enum Element {
     Air,
 }
 
+impl Element {
+    /**
+     * The element after this one, element swap pickups cycle through them in order
+     */
+    pub fn next(&self) -> Element {
+        match self {
+            Element::Water => Element::Fire,
+            Element::Fire => Element::Earth,
+            Element::Earth => Element::Air,
+            Element::Air => Element::Water,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Element::Water => Color::rgb(0.32, 0.32, 1.),
+            Element::Fire => Color::rgb(1., 0.3, 0.25),
+            Element::Earth => Color::rgb(0.5, 0.8, 0.3),
+            Element::Air => Color::rgb(0.85, 0.9, 1.),
+        }
+    }
+}
+
 pub enum ProjectileType {
     Single(Element),
     Compound(Element, Element),
@@ -54,6 +246,8 @@ pub struct ProjectileBundle {
     pub rigidbody: RigidBody,
     pub collider: CollisionShape,
     pub collision_layer: CollisionLayers,
+    pub offscreen: DespawnOffscreen,
+    pub lifetime: Lifetime,
 }
 
 impl Default for ProjectileBundle {
@@ -74,6 +268,42 @@ impl Default for ProjectileBundle {
             faction: Faction::Player,
             collider: CollisionShape::Sphere { radius: 1.0 },
             collision_layer: CollisionLayers::all::<CollisionLayer>(),
+            offscreen: DespawnOffscreen::default(),
+            lifetime: Lifetime::default(),
+        }
+    }
+}
+
+impl ProjectileBundle {
+    /**
+     * A bullet fired by spiders, it only collides with the player
+     */
+    pub fn enemy_bullet(
+        texture: Handle<Image>,
+        position: Vec2,
+        velocity: Vec2,
+        params: BulletParams,
+    ) -> Self {
+        Self {
+            projectile: Projectile { velocity },
+            damage: DealsContactDamage {
+                amount: params.damage,
+            },
+            faction: Faction::Spiders,
+            collider: CollisionShape::Sphere { radius: 4.0 },
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(1., 0.2, 0.3),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(Vec3::from((position, 0.0))),
+                texture,
+                ..Default::default()
+            },
+            collision_layer: CollisionLayers::none()
+                .with_group(CollisionLayer::EnemyProjectile)
+                .with_masks(&[CollisionLayer::Player]),
+            ..Default::default()
         }
     }
 }
@@ -81,58 +311,182 @@ impl Default for ProjectileBundle {
 pub fn spawn_projectile(
     mut commands: Commands,
     mut event: EventReader<SpawnProjectileEvent>,
+    mut pool: ResMut<ProjectilePool>,
     sprite_assets: Res<SpriteAssets>,
 ) {
     for spawn_projectile_event in event.iter() {
-        commands.spawn_bundle(ProjectileBundle {
-            projectile: Projectile {
-                velocity: Vec2::new(
-                    thread_rng().gen_range(-0.75..0.75),
-                    spawn_projectile_event.speed,
-                ),
-            },
-            damage: DealsContactDamage { amount: 25 },
-            faction: Faction::Player,
-            collider: CollisionShape::Sphere { radius: 4.0 },
-            sprite_bundle: SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(0.32, 0.32, 1.),
-                    ..Default::default()
+        let color = match spawn_projectile_event.steering {
+            Some(_) => Color::rgb(1., 0.45, 0.2),
+            None => spawn_projectile_event.element.color(),
+        };
+        let spread = if spawn_projectile_event.spread > 0.0 {
+            thread_rng().gen_range(-spawn_projectile_event.spread..spawn_projectile_event.spread)
+        } else {
+            0.0
+        };
+        let projectile = pool.spawn(
+            &mut commands,
+            ProjectileBundle {
+                projectile: Projectile {
+                    velocity: (spawn_projectile_event.rotation
+                        * Vec3::new(spread, spawn_projectile_event.speed, 0.0))
+                    .truncate(),
                 },
-                transform: Transform {
-                    translation: spawn_projectile_event.position,
-                    rotation: spawn_projectile_event.rotation,
+                damage: DealsContactDamage {
+                    amount: spawn_projectile_event.damage,
+                },
+                faction: Faction::Player,
+                collider: CollisionShape::Sphere { radius: 4.0 },
+                sprite_bundle: SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        translation: spawn_projectile_event.position,
+                        rotation: spawn_projectile_event.rotation,
+                        ..Default::default()
+                    },
+                    texture: sprite_assets.projectile.clone(),
                     ..Default::default()
                 },
-                texture: sprite_assets.projectile.clone(),
+                collision_layer: CollisionLayers::none()
+                    .with_group(CollisionLayer::Player)
+                    .with_masks(&[CollisionLayer::Enemy, CollisionLayer::Hazard]),
                 ..Default::default()
             },
-            collision_layer: CollisionLayers::none()
-                .with_group(CollisionLayer::Player)
-                .with_masks(&[CollisionLayer::Enemy]),
-            ..Default::default()
-        });
+        );
+        if let Some(projectile) = projectile {
+            commands.entity(projectile).insert(Owner {
+                player: spawn_projectile_event.owner,
+            });
+            if let Some(steering) = spawn_projectile_event.steering {
+                commands.entity(projectile).insert(steering);
+            }
+        }
     }
 }
 
-pub fn update_projectile(mut query: Query<(&Projectile, &mut Transform)>) {
+pub fn prune_projectile_pool(
+    mut pool: ResMut<ProjectilePool>,
+    projectiles: Query<(), With<Projectile>>,
+) {
+    pool.prune(|entity| projectiles.get(entity).is_ok());
+}
+
+pub fn update_projectile(
+    time: Res<Time>,
+    mut query: Query<(&Projectile, &mut Transform), Without<Inactive>>,
+) {
     query.for_each_mut(|(projectile, mut transform)| {
-        transform.translation += Vec3::from((projectile.velocity, 0.0));
+        transform.translation += Vec3::from((projectile.velocity, 0.0)) * time.delta_seconds();
     });
 }
 
-pub fn despawn_projectile(
-    mut commands: Commands,
-    query: Query<(Entity, &Transform), With<Projectile>>,
+/**
+ * Applies the steering behaviour of every steered projectile to its velocity
+ * and rotates the projectile to face the direction it is travelling in
+ */
+pub fn steer_projectile(
+    time: Res<Time>,
+    mut projectiles: Query<(&mut Projectile, &mut Steering, &mut Transform), Without<Inactive>>,
+    enemies: Query<(Entity, &GlobalTransform), (With<Enemy>, Without<Projectile>)>,
 ) {
-    const BOUNDS: Vec2 = const_vec2!([500.0, 500.0]);
+    projectiles.for_each_mut(|(mut projectile, mut steering, mut transform)| {
+        let position = transform.translation.xy();
+        match &mut *steering {
+            Steering::Homing { target, turn_rate } => {
+                // Pick a new target if there is none or it no longer exists
+                if target.and_then(|e| enemies.get(e).ok()).is_none() {
+                    *target = nearest_enemy(position, &enemies);
+                }
+                if let Some((_, target_transform)) = target.and_then(|e| enemies.get(e).ok()) {
+                    projectile.velocity = turn_towards(
+                        projectile.velocity,
+                        target_transform.translation.xy() - position,
+                        *turn_rate * time.delta_seconds(),
+                    );
+                }
+            }
+            Steering::Accelerate {
+                acceleration,
+                angular_velocity,
+            } => {
+                let direction = projectile.velocity.normalize_or_zero();
+                let speed =
+                    (projectile.velocity.length() + *acceleration * time.delta_seconds()).max(0.0);
+                projectile.velocity =
+                    Mat2::from_angle(*angular_velocity * time.delta_seconds()) * direction * speed;
+            }
+        }
+        let direction = projectile.velocity;
+        transform.rotation = Quat::from_rotation_z(direction.y.atan2(direction.x) - FRAC_PI_2);
+    });
+}
+
+/**
+ * Drops the target of any projectile homing in on an entity that just died
+ */
+pub fn retarget_on_death(
+    mut death_event: EventReader<OnDeathEvent>,
+    mut query: Query<&mut Steering>,
+) {
+    for event in death_event.iter() {
+        query.for_each_mut(|mut steering| {
+            if let Steering::Homing { target, .. } = &mut *steering {
+                if *target == Some(event.entity) {
+                    *target = None;
+                }
+            }
+        });
+    }
+}
+
+fn nearest_enemy(
+    position: Vec2,
+    enemies: &Query<(Entity, &GlobalTransform), (With<Enemy>, Without<Projectile>)>,
+) -> Option<Entity> {
+    enemies
+        .iter()
+        .map(|(entity, transform)| {
+            (
+                entity,
+                transform.translation.xy().distance_squared(position),
+            )
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
 
-    query.for_each(|(entity, transform)| {
-        // If tranlation is outside of bounds on any axis
-        let xy = transform.translation.xy();
+/**
+ * Rotates `velocity` towards `desired` by at most `max_angle` radians, keeping its length
+ */
+fn turn_towards(velocity: Vec2, desired: Vec2, max_angle: f32) -> Vec2 {
+    if velocity == Vec2::ZERO || desired == Vec2::ZERO {
+        return velocity;
+    }
+    let angle = velocity.angle_between(desired).clamp(-max_angle, max_angle);
+    Mat2::from_angle(angle) * velocity
+}
 
-        if xy.cmpgt(BOUNDS).any() || xy.cmplt(-BOUNDS).any() {
-            commands.entity(entity).despawn_recursive();
+/**
+ * Returns projectiles to the pool once they leave the screen or outlive their lifetime.
+ * Projectiles fired from offscreen and heading away never enter the screen, only the lifetime catches those.
+ */
+pub fn despawn_projectile(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    time: Res<Time>,
+    bounds: Res<ScreenBounds>,
+    mut query: Query<
+        (Entity, &Transform, &mut DespawnOffscreen, &mut Lifetime),
+        (With<Projectile>, Without<Inactive>),
+    >,
+) {
+    query.for_each_mut(|(entity, transform, mut offscreen, mut lifetime)| {
+        let expired = lifetime.0.tick(time.delta()).finished();
+        if offscreen.update(&bounds, transform.translation.xy()) || expired {
+            pool.release(&mut commands, entity);
         }
     });
 }
@@ -140,16 +494,129 @@ pub fn despawn_projectile(
 pub fn projectile_collision(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
+    mut pool: ResMut<ProjectilePool>,
     projectiles: Query<&Projectile>,
 ) {
     for event in collision_events.iter().filter(|e| e.is_started()) {
         let (e1, e2) = event.rigid_body_entities();
         // Check if one of the entities has a projectile component
-        // If it collided destroy the projectile
+        // If it collided return the projectile to the pool
         if projectiles.get_component::<Projectile>(e1).is_ok() {
-            commands.entity(e1).despawn_recursive();
+            pool.release(&mut commands, e1);
         } else if projectiles.get_component::<Projectile>(e2).is_ok() {
-            commands.entity(e2).despawn_recursive();
+            pool.release(&mut commands, e2);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    /**
+     * Runs `f` with a fresh set of commands and applies them to the world afterwards
+     */
+    fn with_commands<T>(world: &mut World, f: impl FnOnce(&mut Commands) -> T) -> T {
+        let mut queue = CommandQueue::default();
+        let result = f(&mut Commands::new(&mut queue, world));
+        queue.apply(world);
+        result
+    }
+
+    #[test]
+    fn released_projectiles_are_reused() {
+        let mut world = World::new();
+        let mut pool = ProjectilePool::with_capacity(8);
+
+        let first = with_commands(&mut world, |commands| {
+            pool.spawn(commands, ProjectileBundle::default())
+        })
+        .unwrap();
+        with_commands(&mut world, |commands| pool.release(commands, first));
+        assert!(world.get::<Inactive>(first).is_some());
+        assert_eq!(pool.active(), 0);
+
+        let second = with_commands(&mut world, |commands| {
+            pool.spawn(commands, ProjectileBundle::default())
+        })
+        .unwrap();
+        assert_eq!(first, second);
+        assert!(world.get::<Inactive>(second).is_none());
+        assert_eq!(pool.active(), 1);
+        assert_eq!(world.entities().len(), 1);
+    }
+
+    #[test]
+    fn spawn_fails_at_capacity() {
+        let mut world = World::new();
+        let mut pool = ProjectilePool::with_capacity(2);
+
+        let spawned: Vec<Option<Entity>> = with_commands(&mut world, |commands| {
+            (0..3)
+                .map(|_| pool.spawn(commands, ProjectileBundle::default()))
+                .collect()
+        });
+        assert!(spawned[0].is_some());
+        assert!(spawned[1].is_some());
+        assert!(spawned[2].is_none());
+        assert_eq!(pool.active(), 2);
+    }
+
+    #[test]
+    fn despawned_projectiles_are_not_reused() {
+        let mut world = World::new();
+        let mut pool = ProjectilePool::with_capacity(2);
+
+        let (released, despawned) = with_commands(&mut world, |commands| {
+            (
+                pool.spawn(commands, ProjectileBundle::default()).unwrap(),
+                pool.spawn(commands, ProjectileBundle::default()).unwrap(),
+            )
+        });
+        with_commands(&mut world, |commands| pool.release(commands, released));
+        // Both despawned behind the pool's back, e.g. by despawn_recursive on a parent
+        world.despawn(released);
+        world.despawn(despawned);
+
+        pool.prune(|entity| world.get_entity(entity).is_some());
+        assert_eq!(pool.active(), 0);
+
+        let spawned = with_commands(&mut world, |commands| {
+            pool.spawn(commands, ProjectileBundle::default())
+        })
+        .unwrap();
+        assert_ne!(spawned, released);
+        assert_ne!(spawned, despawned);
+        assert!(world.get::<Projectile>(spawned).is_some());
+    }
+
+    #[test]
+    fn double_release_is_harmless() {
+        let mut world = World::new();
+        let mut pool = ProjectilePool::with_capacity(2);
+
+        let projectile = with_commands(&mut world, |commands| {
+            pool.spawn(commands, ProjectileBundle::default())
+        })
+        .unwrap();
+        // Hit something and left the screen in the same frame
+        with_commands(&mut world, |commands| {
+            pool.release(commands, projectile);
+            pool.release(commands, projectile);
+        });
+        assert_eq!(pool.active(), 0);
+
+        // Had it been freed twice both of these would get the same entity
+        let (first, second) = with_commands(&mut world, |commands| {
+            (
+                pool.spawn(commands, ProjectileBundle::default()),
+                pool.spawn(commands, ProjectileBundle::default()),
+            )
+        });
+        assert_eq!(first, Some(projectile));
+        assert!(second.is_some());
+        assert_ne!(first, second);
+    }
+}
@@ -5,12 +5,24 @@ use crate::prelude::*;
 
 use self::orchestration::*;
 
+/**
+ * Sent by a wave to tell enemies something is happening, see Trigger::WaveSignal
+ */
+pub struct WaveSignalEvent {
+    pub name: String,
+}
+
 pub struct StagePlugin;
 
 impl Plugin for StagePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<StageOrchestrationState>();
-        app.add_enter_system(AppState::Game(Running), stage_startup);
+        app.add_event::<WaveSignalEvent>();
+        // Coming back from the pause menu doesn't start the stage over
+        app.add_enter_system(
+            AppState::Game(Running),
+            stage_startup.run_unless_resource_exists::<Resuming>(),
+        );
         app.add_system_set(
             ConditionSet::new()
                 .run_in_state(AppState::Game(Running))
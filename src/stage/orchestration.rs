@@ -1,8 +1,15 @@
-use rand::{thread_rng, Rng};
+use std::collections::HashSet;
+
+use rand::{seq::SliceRandom, thread_rng, Rng};
 
 use super::waves::*;
 use crate::prelude::*;
 
+/**
+ * How much harder every loop of the endless stage starts than the one before
+ */
+const LOOP_DIFFICULTY: u32 = 5;
+
 pub struct StageOrchestrationState {
     pub wave: Option<Wave>,
     pub started: bool,
@@ -10,6 +17,11 @@ pub struct StageOrchestrationState {
     pub tick_timer: i32,
     pub current_action: Option<WaveAction>,
     pub alive_enemies: i32,
+    pub alive_egg_sacs: i32,
+    /**
+     * Times the stage has been cleared, it starts over harder every time
+     */
+    pub loops: u32,
 }
 
 impl Default for StageOrchestrationState {
@@ -21,60 +33,129 @@ impl Default for StageOrchestrationState {
             tick_timer: 0,
             current_action: None,
             alive_enemies: 0,
+            alive_egg_sacs: 0,
+            loops: 0,
         }
     }
 }
 
-pub fn stage_startup(mut state: ResMut<StageOrchestrationState>) {
-    state.wave = Some(random_wave());
-    state.started = true;
+pub fn stage_startup(
+    mut state: ResMut<StageOrchestrationState>,
+    definitions: Res<EnemyDefinitions>,
+) {
+    // Nothing carries over from a previous game, in particular the enemy counts
+    *state = StageOrchestrationState {
+        wave: Some(random_wave(&definitions, 0)),
+        started: true,
+        ..Default::default()
+    };
 }
 
-fn random_wave() -> Wave {
+/**
+ * Grids of enemies picked by their wave_weight, with the odd pair of egg sacs and a boss at the end.
+ * Enemies get elite modifiers more often the higher the difficulty, which also rises with every grid.
+ */
+fn random_wave(definitions: &EnemyDefinitions, difficulty: u32) -> Wave {
+    let candidates: Vec<(&EnemyType, u32)> = definitions
+        .enemies
+        .iter()
+        .map(|(enemy_type, definition)| (enemy_type, definition.wave_weight))
+        .filter(|(_, weight)| *weight > 0)
+        .collect();
     let mut builder = WaveBuilder::new();
-    for _ in 4..thread_rng().gen_range(10..16) {
+    let grids = thread_rng().gen_range(6..12);
+    for grid in 0..grids {
+        // The last grid is still around when the boss arrives, see below
+        let last = grid + 1 == grids;
+        let difficulty = difficulty + grid / 2;
         let rows: i32 = thread_rng().gen_range(1..4);
         let columns: i32 = thread_rng().gen_range(2..6);
-        let enemy_type = match thread_rng().gen_range(0..3) {
-            0 => EnemyType::MediumSpider,
-            1 => EnemyType::SmallSpider,
-            2 => EnemyType::TinySpider,
-            _ => panic!("Bruh thats not an enemy"),
+        let enemy_type = match candidates.choose_weighted(&mut thread_rng(), |(_, weight)| *weight)
+        {
+            Ok((enemy_type, _)) => (*enemy_type).clone(),
+            Err(_) => {
+                error!("No enemies can appear in random waves");
+                break;
+            }
         };
         let next_spawn_delay: f32 = thread_rng().gen_range(1.0..3.0);
 
+        if !last && thread_rng().gen_bool(0.15) {
+            // A pair of egg sacs that have to be destroyed before the stage goes on
+            builder.spawn_at(EnemyType::new("egg_sac"), Vec2::new(-150.0, 200.0));
+            builder.spawn_at(EnemyType::new("egg_sac"), Vec2::new(150.0, 200.0));
+            builder.wait_for(Condition::AllEggSacsDestroyed);
+            builder.wait_sec(next_spawn_delay);
+            continue;
+        }
+
         let spacing = 96.0;
         let startx = -((((columns - 1) as f32) * spacing) / 2.0);
         let starty = (((rows - 1) as f32) * spacing) / 2.0;
         for column in 0..columns {
             for row in 0..rows {
-                builder.spawn_at(
-                    enemy_type,
+                builder.spawn_elite_at(
+                    enemy_type.clone(),
                     Vec2::new(
                         startx + (column as f32 * spacing),
                         starty + (row as f32 * spacing),
                     ),
+                    EliteModifier::roll(difficulty),
                 );
             }
         }
-        builder.wait_for(Condition::PreviousWaveHasDied);
+        if !last {
+            builder.wait_for(Condition::PreviousWaveHasDied);
+        }
         builder.wait_sec(next_spawn_delay);
     }
+    // Every stage ends with a boss. The signal goes out while the last grid is still alive
+    // so its spiders can make way, the stage then waits for them and the boss together
+    builder.signal("boss_incoming");
+    builder.spawn_at(EnemyType::new("boss_spider"), Vec2::new(0.0, 480.0));
+    builder.wait_for(Condition::PreviousWaveHasDied);
     builder.build()
 }
 
 pub fn on_enemy_death(
     mut enemy_death: EventReader<OnDeathEvent>,
+    mut enemy_offscreen: EventReader<OnDespawnOffscreenEvent>,
+    mut enemy_hatch: EventReader<OnHatchEvent>,
     mut state: ResMut<StageOrchestrationState>,
+    definitions: Res<EnemyDefinitions>,
 ) {
-    for _ in enemy_death.iter() {
+    for _ in enemy_hatch.iter() {
+        state.alive_enemies += 1;
+    }
+    let mut dead = HashSet::new();
+    for event in enemy_death.iter() {
+        dead.insert(event.entity);
         state.alive_enemies -= 1;
+        let enemy_type = match &event.enemy_type {
+            Some(enemy_type) => enemy_type,
+            None => continue,
+        };
+        if definitions.hatches(enemy_type) {
+            state.alive_egg_sacs -= 1;
+        }
+        // Counted here rather than when they spawn so the wave can't end in between
+        for split in definitions.splits(enemy_type, &event.modifiers) {
+            state.alive_enemies += split.count as i32;
+        }
+    }
+    // Enemies that fly off screen won't come back, unless they were counted as dead already
+    for event in enemy_offscreen.iter() {
+        if !dead.contains(&event.entity) {
+            state.alive_enemies -= 1;
+        }
     }
 }
 
 pub fn stage_orchestration(
     mut spawn_enemy: EventWriter<SpawnEnemyEvent>,
+    mut wave_signal: EventWriter<WaveSignalEvent>,
     mut state: ResMut<StageOrchestrationState>,
+    definitions: Res<EnemyDefinitions>,
     time: Res<Time>,
 ) {
     if state.started {
@@ -87,17 +168,41 @@ pub fn stage_orchestration(
             return;
         }
 
-        while let Some(action) = state.wave.as_mut().unwrap().next_action() {
+        loop {
+            let action = match state.wave.as_mut().unwrap().next_action() {
+                Some(action) => action,
+                // Endless play, the stage starts over with a harder wave once the boss is out
+                None => {
+                    state.loops += 1;
+                    state.wave = Some(random_wave(&definitions, state.loops * LOOP_DIFFICULTY));
+                    state.current_action = None;
+                    break;
+                }
+            };
             state.current_action = Some(action);
 
             match state.current_action.as_ref().unwrap() {
                 WaveAction::Spawn(spawn) => match spawn {
-                    EnemySpawn::At { enemy, position } => {
+                    EnemySpawn::At {
+                        enemy,
+                        position,
+                        modifiers,
+                    } => {
+                        // Unknown enemies would never die and hold up the stage
+                        if definitions.get(enemy).is_none() {
+                            error!("There is no enemy called {:?}", enemy);
+                            continue;
+                        }
                         spawn_enemy.send(SpawnEnemyEvent {
                             position: position.clone(),
                             enemy_type: enemy.clone(),
+                            velocity: Vec2::ZERO,
+                            modifiers: modifiers.clone(),
                         });
                         state.alive_enemies += 1;
+                        if definitions.hatches(enemy) {
+                            state.alive_egg_sacs += 1;
+                        }
                     }
                     EnemySpawn::Inside => todo!(),
                 },
@@ -110,8 +215,11 @@ pub fn stage_orchestration(
                     };
                     break;
                 }
+                WaveAction::Signal(name) => {
+                    wave_signal.send(WaveSignalEvent { name: name.clone() })
+                }
                 WaveAction::Condition(condition) => match condition {
-                    Condition::PreviousWaveHasDied => break,
+                    Condition::PreviousWaveHasDied | Condition::AllEggSacsDestroyed => break,
                     _ => todo!("not yet implemented!"),
                 },
             }
@@ -144,6 +252,11 @@ fn can_continue_orchestration(state: &mut StageOrchestrationState, time: &Time)
                             return false;
                         }
                     }
+                    Condition::AllEggSacsDestroyed => {
+                        if state.alive_egg_sacs > 0 {
+                            return false;
+                        }
+                    }
                     Condition::And(_) => todo!("chaining conditions not yet implemented!"),
                     Condition::Or(_) => todo!("chaining conditions not yet implemented!"),
                 };
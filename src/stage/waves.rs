@@ -5,11 +5,18 @@ use std::collections::{
 
 use bevy::math::Vec2;
 
-use crate::prelude::EnemyType;
+use crate::prelude::{EliteModifier, EnemyType};
 
 #[derive(Debug)]
 pub enum EnemySpawn {
-    At { enemy: EnemyType, position: Vec2 },
+    At {
+        enemy: EnemyType,
+        position: Vec2,
+        /**
+         * Makes the enemy an elite, empty for a regular one
+         */
+        modifiers: Vec<EliteModifier>,
+    },
     Inside,
 }
 
@@ -22,6 +29,10 @@ pub enum Delay {
 #[derive(Debug)]
 pub enum Condition {
     PreviousWaveHasDied,
+    /**
+     * Every egg sac spawned so far has been destroyed, the spiders they hatched may still be alive
+     */
+    AllEggSacsDestroyed,
     And(Vec<Condition>),
     Or(Vec<Condition>),
 }
@@ -31,6 +42,10 @@ pub enum WaveAction {
     Spawn(EnemySpawn),
     Delay(Delay),
     Condition(Condition),
+    /**
+     * Tells the enemies on screen something, see WaveSignalEvent
+     */
+    Signal(String),
 }
 
 /**
@@ -85,8 +100,24 @@ impl WaveBuilder {
      */
     #[inline]
     pub fn spawn_at(&mut self, enemy: EnemyType, position: Vec2) -> &WaveBuilder {
-        self.actions
-            .push(WaveAction::Spawn(EnemySpawn::At { enemy, position }));
+        self.spawn_elite_at(enemy, position, Vec::new())
+    }
+
+    /**
+     * Spawn a single enemy with elite modifiers
+     */
+    #[inline]
+    pub fn spawn_elite_at(
+        &mut self,
+        enemy: EnemyType,
+        position: Vec2,
+        modifiers: Vec<EliteModifier>,
+    ) -> &WaveBuilder {
+        self.actions.push(WaveAction::Spawn(EnemySpawn::At {
+            enemy,
+            position,
+            modifiers,
+        }));
         self
     }
 
@@ -99,6 +130,15 @@ impl WaveBuilder {
         self
     }
 
+    /**
+     * Send a signal to the enemies on screen
+     */
+    #[inline]
+    pub fn signal(&mut self, name: &str) -> &WaveBuilder {
+        self.actions.push(WaveAction::Signal(name.to_string()));
+        self
+    }
+
     /**
      * Wait seconds until next spawn
      */
@@ -8,6 +8,7 @@ pub enum AppState {
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub enum MenuState {
     StartMenu,
+    Settings,
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
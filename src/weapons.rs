@@ -0,0 +1,348 @@
+use std::fs;
+
+use heron::rapier_plugin::PhysicsWorld;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const WEAPON_LEVELS_PATH: &str = "assets/data/weapon_levels.ron";
+
+const BOMB_DAMAGE: i32 = 500;
+/**
+ * Seconds the bombing player can't be hurt
+ */
+const BOMB_INVULNERABILITY: f32 = 2.0;
+const BOMB_FLASH_SECONDS: f32 = 0.4;
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WeaponLevels::load_or_default(WEAPON_LEVELS_PATH));
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(AppState::Game(Running))
+                .with_system(attach_beam)
+                .with_system(update_beam)
+                .with_system(player_bomb)
+                .with_system(fade_bomb_flash)
+                .with_system(lose_weapon_level)
+                .into(),
+        );
+    }
+}
+
+/**
+ * The hardpoints of one weapon level
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeaponLayout {
+    pub hardpoints: Vec<Hardpoint>,
+    #[serde(default)]
+    pub rolling_fire: bool,
+}
+
+/**
+ * Hardpoint layouts of the main gun for every weapon level, read from a data file so they
+ * can be tuned without recompiling
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeaponLevels {
+    pub levels: Vec<WeaponLayout>,
+}
+
+impl Default for WeaponLevels {
+    fn default() -> Self {
+        let hardpoint = |x: f32| Hardpoint {
+            offset: Vec2::new(x, 0.0),
+            angle: 0.0,
+            damage: 25,
+            speed: 1000.0,
+        };
+        Self {
+            levels: vec![WeaponLayout {
+                hardpoints: vec![hardpoint(-14.0), hardpoint(14.0)],
+                rolling_fire: true,
+            }],
+        }
+    }
+}
+
+impl WeaponLevels {
+    /**
+     * Reads the weapon levels from a data file, falling back to a single level if it is missing or invalid
+     */
+    pub fn load_or_default(path: &str) -> Self {
+        let result = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str::<Self>(&contents).map_err(|err| err.to_string()));
+        match result {
+            Ok(levels) if !levels.levels.is_empty() => levels,
+            Ok(_) => {
+                warn!("{} has no weapon levels, using the default", path);
+                Self::default()
+            }
+            Err(err) => {
+                warn!("Could not load {}, using the default weapon: {}", path, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn max_level(&self) -> usize {
+        self.levels.len()
+    }
+
+    /**
+     * The layout of a weapon level, levels start at 1
+     */
+    pub fn layout(&self, level: usize) -> Option<&WeaponLayout> {
+        self.levels.get(level.checked_sub(1)?)
+    }
+}
+
+/**
+ * A continuous beam which damages everything it touches every second instead of per hit
+ */
+#[derive(Component)]
+pub struct BeamEmitter {
+    pub firing: bool,
+    pub damage_per_second: f32,
+    pub range: f32,
+    pub width: f32,
+    /**
+     * Distance from the emitters origin to where the beam starts
+     */
+    pub offset: f32,
+    /**
+     * Whether the beam passes through enemies or stops at the first one
+     */
+    pub pierce: bool,
+    /**
+     * Damage that has been dealt in fractions of a health point, applied once it adds up
+     */
+    pub damage_buffer: f32,
+}
+
+impl Default for BeamEmitter {
+    fn default() -> Self {
+        Self {
+            firing: false,
+            damage_per_second: 120.0,
+            range: 800.0,
+            width: 12.0,
+            offset: 24.0,
+            pierce: false,
+            damage_buffer: 0.0,
+        }
+    }
+}
+
+/**
+ * The visual part of a beam, stretched to the length of the beam each frame
+ */
+#[derive(Component)]
+pub struct Beam;
+
+/**
+ * Gives every new beam emitter its beam sprite
+ */
+pub fn attach_beam(
+    mut commands: Commands,
+    sprite_assets: Res<SpriteAssets>,
+    query: Query<Entity, Added<BeamEmitter>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    texture: sprite_assets.beam_projectile.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, -0.1),
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(Beam);
+        });
+    }
+}
+
+/**
+ * Casts a ray for each firing beam, damages whatever it hits and stretches the beam sprite to fit
+ */
+pub fn update_beam(
+    time: Res<Time>,
+    physics_world: PhysicsWorld,
+    mut emitters: Query<(
+        &mut BeamEmitter,
+        &GlobalTransform,
+        &Children,
+        Option<&Owner>,
+    )>,
+    mut beams: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<Beam>>,
+    mut healthy: Query<(&mut Health, Option<&mut Shield>, Option<&mut LastHitBy>), With<Enemy>>,
+    mut on_damage: EventWriter<OnDamageEvent>,
+) {
+    for (mut emitter, global_transform, children, owner) in emitters.iter_mut() {
+        let mut length = emitter.range;
+
+        if emitter.firing {
+            let direction = global_transform.rotation * Vec3::Y;
+            let start = global_transform.translation + direction * emitter.offset;
+            let layers = CollisionLayers::none()
+                .with_group(CollisionLayer::Player)
+                .with_masks(&[CollisionLayer::Enemy]);
+
+            let mut hits = Vec::new();
+            let mut origin = start;
+            // Heron only reports the closest hit, so piercing beams cast again past every enemy they hit
+            while let Some(hit) = physics_world.ray_cast_with_filter(
+                origin,
+                start + direction * emitter.range - origin,
+                true,
+                layers,
+                |entity| !hits.contains(&entity),
+            ) {
+                hits.push(hit.entity);
+                if !emitter.pierce {
+                    length = (hit.collision_point - start).length();
+                    break;
+                }
+                origin = hit.collision_point;
+            }
+
+            emitter.damage_buffer += emitter.damage_per_second * time.delta_seconds();
+            let damage = emitter.damage_buffer.floor();
+            emitter.damage_buffer -= damage;
+
+            for entity in hits.into_iter().filter(|_| damage > 0.0) {
+                if let Ok((mut health, mut shield, last_hit_by)) = healthy.get_mut(entity) {
+                    // Armor is ignored since the beam deals its damage a little every frame
+                    let damage =
+                        deal_damage(&mut health, shield.as_deref_mut(), None, damage as i32);
+                    on_damage.send(OnDamageEvent { entity, damage });
+                    if let (Some(mut last_hit_by), Some(owner)) = (last_hit_by, owner) {
+                        last_hit_by.player = Some(owner.player);
+                    }
+                }
+            }
+        } else {
+            emitter.damage_buffer = 0.0;
+        }
+
+        for &child in children.iter() {
+            if let Ok((mut sprite, mut transform, mut visibility)) = beams.get_mut(child) {
+                visibility.is_visible = emitter.firing;
+                sprite.custom_size = Some(Vec2::new(emitter.width, length));
+                transform.translation.y = emitter.offset + length / 2.0;
+            }
+        }
+    }
+}
+
+/**
+ * White screen flash shown when a bomb goes off
+ */
+#[derive(Component)]
+pub struct BombFlash {
+    pub timer: Timer,
+}
+
+/**
+ * Uses up a bomb charge to damage every enemy on screen and clear all enemy bullets
+ */
+pub fn player_bomb(
+    mut commands: Commands,
+    player_actions: Res<PlayerActions>,
+    mut players: ResMut<Players>,
+    mut pool: ResMut<ProjectilePool>,
+    bounds: Res<ScreenBounds>,
+    ships: Query<(Entity, &Player)>,
+    mut enemies: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            Option<&mut LastHitBy>,
+        ),
+        With<Enemy>,
+    >,
+    bullets: Query<(Entity, &Faction), (With<Projectile>, Without<Inactive>)>,
+    mut on_damage: EventWriter<OnDamageEvent>,
+) {
+    for (entity, player) in ships.iter() {
+        let slot = &mut players.slots[player.index];
+        if !player_actions.get(player.index).just_pressed(Action::Bomb) || slot.bombs == 0 {
+            continue;
+        }
+        slot.bombs -= 1;
+
+        for (enemy, transform, mut health, mut shield, armor, last_hit_by) in enemies.iter_mut() {
+            if bounds.contains(transform.translation.truncate(), 0.0) {
+                let damage = deal_damage(&mut health, shield.as_deref_mut(), armor, BOMB_DAMAGE);
+                on_damage.send(OnDamageEvent {
+                    entity: enemy,
+                    damage,
+                });
+                if let Some(mut last_hit_by) = last_hit_by {
+                    last_hit_by.player = Some(player.index);
+                }
+            }
+        }
+        for (bullet, faction) in bullets.iter() {
+            if *faction == Faction::Spiders {
+                pool.release(&mut commands, bullet);
+            }
+        }
+
+        commands
+            .entity(entity)
+            .insert(Invulnerable::new(BOMB_INVULNERABILITY));
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.8),
+                    custom_size: Some(bounds.max - bounds.min),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(
+                    ((bounds.min + bounds.max) / 2.0).extend(10.0),
+                ),
+                ..Default::default()
+            })
+            .insert(BombFlash {
+                timer: Timer::from_seconds(BOMB_FLASH_SECONDS, false),
+            });
+    }
+}
+
+pub fn fade_bomb_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut BombFlash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        if flash.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(0.8 * flash.timer.percent_left());
+        }
+    }
+}
+
+/**
+ * Taking a hit that gets through the shield costs a player one weapon level
+ */
+pub fn lose_weapon_level(
+    mut on_damage: EventReader<OnDamageEvent>,
+    weapon_levels: Res<WeaponLevels>,
+    mut weapons: Query<&mut Armed, With<Player>>,
+) {
+    for event in on_damage.iter().filter(|event| event.damage.health > 0) {
+        if let Ok(mut armed) = weapons.get_mut(event.entity) {
+            let level = armed.level.saturating_sub(1);
+            armed.set_level(level, &weapon_levels);
+        }
+    }
+}